@@ -0,0 +1,135 @@
+extern crate piston_window;
+extern crate image;
+
+use piston_window::*;
+use crate::entities::{AlienSpecies, AlienVariant, ShotKind, Sprite};
+use crate::error::GameError;
+use std::collections::HashMap;
+use std::path::Path;
+
+// Environment variable pointing to a directory with images that replace the embedded ones
+pub const ASSETS_DIRECTORY_VARIABLE: &str = "INVADERS_ASSETS_DIR";
+
+// Images bundled into the executable, by file name
+const EMBEDDED_ASSETS: &[(&str, &[u8])] = &[
+    ("alien-shot.png", include_bytes!("assets/alien-shot.png")),
+    ("barricade-64x64.png", include_bytes!("assets/barricade-64x64.png")),
+    ("bug-alien-32x32.png", include_bytes!("assets/bug-alien-32x32.png")),
+    ("bug-alien-blue-32x32.png", include_bytes!("assets/bug-alien-blue-32x32.png")),
+    ("bug-alien-pink-32x32.png", include_bytes!("assets/bug-alien-pink-32x32.png")),
+    ("bug-alien-white-32x32.png", include_bytes!("assets/bug-alien-white-32x32.png")),
+    ("bug-alien.png", include_bytes!("assets/bug-alien.png")),
+    ("cannon-32x32.png", include_bytes!("assets/cannon-32x32.png")),
+    ("cannon-ball-18x18.png", include_bytes!("assets/cannon-ball-18x18.png")),
+    ("cannon.png", include_bytes!("assets/cannon.png")),
+    ("plunger-shot-9x24.png", include_bytes!("assets/plunger-shot-9x24.png")),
+    ("rolling-shot-9x24.png", include_bytes!("assets/rolling-shot-9x24.png")),
+    ("soldier-alien-32x32.png", include_bytes!("assets/soldier-alien-32x32.png")),
+    ("soldier-alien-blue-32x32.png", include_bytes!("assets/soldier-alien-blue-32x32.png")),
+    ("soldier-alien-pink-32x32.png", include_bytes!("assets/soldier-alien-pink-32x32.png")),
+    ("soldier-alien-white-32x32.png", include_bytes!("assets/soldier-alien-white-32x32.png")),
+    ("soldier-alien.png", include_bytes!("assets/soldier-alien.png")),
+    ("squid-alien-32x32.png", include_bytes!("assets/squid-alien-32x32.png")),
+    ("squid-alien-blue-32x32.png", include_bytes!("assets/squid-alien-blue-32x32.png")),
+    ("squid-alien-pink-32x32.png", include_bytes!("assets/squid-alien-pink-32x32.png")),
+    ("squid-alien-white-32x32.png", include_bytes!("assets/squid-alien-white-32x32.png")),
+    ("squid-alien.png", include_bytes!("assets/squid-alien.png")),
+    ("squiggly-shot-9x24.png", include_bytes!("assets/squiggly-shot-9x24.png")),
+];
+
+// Index of a texture loaded by the AssetManager
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AssetId(usize);
+
+// Loads every embedded image once and shares it by AssetId
+pub struct AssetManager {
+    textures: Vec<G2dTexture>,
+    ids: HashMap<String, AssetId>,
+    sprites: HashMap<Sprite, AssetId>,
+}
+
+impl AssetManager {
+    // Images found in `override_directory` with the same file name as an embedded one are used instead of it
+    pub fn load(window: &mut PistonWindow, override_directory: Option<&Path>) -> Result<AssetManager, GameError> {
+        let mut asset_manager = AssetManager {
+            textures: vec![],
+            ids: HashMap::new(),
+            sprites: HashMap::new(),
+        };
+
+        let mut texture_context = window.create_texture_context();
+        for (file_name, bytes) in EMBEDDED_ASSETS {
+            let override_path = override_directory.map(|directory| directory.join(file_name));
+
+            let img = match override_path {
+                Some(path) if path.is_file() => image::open(&path)
+                    .map_err(|error| GameError::AssetLoad(format!("{}: {}", path.display(), error)))?,
+                _ => image::load_from_memory(bytes)
+                    .map_err(|error| GameError::AssetLoad(format!("embedded {}: {}", file_name, error)))?,
+            };
+
+            let texture = Texture::from_image(
+                &mut texture_context,
+                &img.to_rgba8(),
+                &TextureSettings::new()
+            ).map_err(|error| GameError::AssetLoad(format!("texture for {}: {:?}", file_name, error)))?;
+
+            asset_manager.ids.insert(file_name.to_string(), AssetId(asset_manager.textures.len()));
+            asset_manager.textures.push(texture);
+        }
+
+        for sprite in Sprite::all() {
+            if let Some(file_name) = sprite_file_name(sprite) {
+                match asset_manager.get_id(&file_name) {
+                    Some(id) => { asset_manager.sprites.insert(sprite, id); },
+                    None => return Err(GameError::AssetLoad(format!("missing {} for sprite {:?}", file_name, sprite))),
+                }
+            }
+        }
+
+        Ok(asset_manager)
+    }
+
+    pub fn get_id(&self, file_name: &str) -> Option<AssetId> {
+        self.ids.get(file_name).copied()
+    }
+
+    pub fn get_texture(&self, id: AssetId) -> &G2dTexture {
+        &self.textures[id.0]
+    }
+
+    pub fn get_sprite_texture(&self, sprite: Sprite) -> Option<&G2dTexture> {
+        self.sprites.get(&sprite).map(|id| self.get_texture(*id))
+    }
+}
+
+// Sprites without an image are drawn with shapes by the render layer
+pub fn sprite_file_name(sprite: Sprite) -> Option<String> {
+    match sprite {
+        Sprite::Cannon => Some(String::from("cannon-32x32.png")),
+        Sprite::CannonShot => Some(String::from("cannon-ball-18x18.png")),
+        Sprite::AlienShot(kind) => match kind {
+            ShotKind::Straight => Some(String::from("alien-shot.png")),
+            ShotKind::Squiggly => Some(String::from("squiggly-shot-9x24.png")),
+            ShotKind::Plunger => Some(String::from("plunger-shot-9x24.png")),
+            ShotKind::Rolling => Some(String::from("rolling-shot-9x24.png")),
+        },
+        Sprite::Barricade => Some(String::from("barricade-64x64.png")),
+        Sprite::Ufo => None,
+        Sprite::Alien(species, variant) => {
+            let species = match species {
+                AlienSpecies::Bug => "bug-alien-",
+                AlienSpecies::Soldier => "soldier-alien-",
+                AlienSpecies::Squid => "squid-alien-",
+            };
+            let variant = match variant {
+                AlienVariant::Default => "",
+                AlienVariant::White => "white-",
+                AlienVariant::Pink => "pink-",
+                AlienVariant::Blue => "blue-",
+            };
+
+            Some(format!("{}{}32x32.png", species, variant))
+        },
+    }
+}
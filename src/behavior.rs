@@ -0,0 +1,180 @@
+use crate::entities::{Alien, AlienSpecies, Dive, Shot, ShotKind};
+use rand::rngs::StdRng;
+use rand::Rng;
+
+// Soldier shots never drift sideways faster than this fraction of their falling speed
+const SOLDIER_MAX_AIM: f64 = 0.5;
+
+// Chance per second of each bug leaving the formation, while fewer than MAX_DIVING_BUGS are diving
+const BUG_DIVE_CHANCE: f64 = 0.01;
+const MAX_DIVING_BUGS: usize = 2;
+const BUG_DIVE_SPEED: f64 = 150.0;
+
+// What the aliens can see of the world during a step
+#[derive(Clone, Copy, Debug)]
+pub struct BehaviorContext {
+    // Center of the top of the cannon
+    pub cannon_x: f64,
+    pub cannon_y: f64,
+    pub world_height: f64,
+    pub diving_count: usize,
+}
+
+// How an alien species acts, on top of marching with the formation
+pub trait AlienBehavior {
+    // Kind of the shots of the species, unless the wave chooses one
+    fn shot_kind(&self) -> ShotKind {
+        ShotKind::Straight
+    }
+
+    // Shot of the given kind fired by `alien`, `movement_speed` in pixels per second
+    fn shoot(&self, alien: &mut Alien, _context: &BehaviorContext, kind: ShotKind, movement_speed: f64) -> Shot {
+        alien.shoot(kind, movement_speed)
+    }
+
+    // Called every step before the formation moves, `dt` is the elapsed time in seconds
+    fn update(&self, _alien: &mut Alien, _context: &BehaviorContext, _rng: &mut StdRng, _dt: f64) {}
+}
+
+pub struct SquidBehavior;
+pub struct SoldierBehavior;
+pub struct BugBehavior;
+
+pub fn behavior(species: AlienSpecies) -> &'static dyn AlienBehavior {
+    match species {
+        AlienSpecies::Squid => &SquidBehavior,
+        AlienSpecies::Soldier => &SoldierBehavior,
+        AlienSpecies::Bug => &BugBehavior,
+    }
+}
+
+// Squids fire fast zig-zag shots
+impl AlienBehavior for SquidBehavior {
+    fn shot_kind(&self) -> ShotKind {
+        ShotKind::Squiggly
+    }
+}
+
+impl AlienBehavior for SoldierBehavior {
+    fn shot_kind(&self) -> ShotKind {
+        ShotKind::Plunger
+    }
+
+    // Aimed to reach the height of the cannon where it was when fired, rolling shots already fall on it
+    fn shoot(&self, alien: &mut Alien, context: &BehaviorContext, kind: ShotKind, movement_speed: f64) -> Shot {
+        let mut shot = alien.shoot(kind, movement_speed);
+        if kind == ShotKind::Rolling {
+            return shot;
+        }
+
+        let shot_speed = shot.entity.movement_speed;
+        let shot_center = shot.entity.x + (shot.entity.width / 2.0);
+        let fall_time = shot.get_fall_time((context.cannon_y - shot.entity.y).max(1.0));
+        let max_speed_x = shot_speed * SOLDIER_MAX_AIM;
+
+        shot.speed_x = ((context.cannon_x - shot_center) / fall_time).clamp(-max_speed_x, max_speed_x);
+        shot
+    }
+}
+
+impl AlienBehavior for BugBehavior {
+    // Dive at the cannon, come back from the top of the screen and fly back to the formation
+    fn update(&self, alien: &mut Alien, context: &BehaviorContext, rng: &mut StdRng, dt: f64) {
+        match alien.dive.as_mut() {
+            None => {
+                if context.diving_count < MAX_DIVING_BUGS && rng.gen_bool((BUG_DIVE_CHANCE * dt).min(1.0)) {
+                    let distance_x = context.cannon_x - (alien.entity.x + (alien.entity.width / 2.0));
+                    let distance_y = (context.cannon_y - alien.entity.y).max(1.0);
+                    let distance = distance_x.hypot(distance_y);
+
+                    alien.dive = Some(Dive {
+                        velocity: (distance_x / distance * BUG_DIVE_SPEED, distance_y / distance * BUG_DIVE_SPEED),
+                        offset: (0.0, 0.0),
+                        returning: false,
+                    });
+                }
+            },
+            Some(dive) if !dive.returning => {
+                let movement = (dive.velocity.0 * dt, dive.velocity.1 * dt);
+                alien.entity.x += movement.0;
+                alien.entity.y += movement.1;
+                dive.offset = (dive.offset.0 + movement.0, dive.offset.1 + movement.1);
+
+                // Past the bottom it comes back from the top
+                if alien.entity.y > context.world_height {
+                    let jump = context.world_height + (alien.entity.height * 2.0);
+                    alien.entity.y -= jump;
+                    dive.offset.1 -= jump;
+                    dive.returning = true;
+                }
+            },
+            Some(dive) => {
+                let distance = dive.offset.0.hypot(dive.offset.1);
+                let step = BUG_DIVE_SPEED * dt;
+
+                if distance <= step {
+                    alien.entity.x -= dive.offset.0;
+                    alien.entity.y -= dive.offset.1;
+                    alien.dive = None;
+                } else {
+                    let movement = (dive.offset.0 / distance * step, dive.offset.1 / distance * step);
+                    alien.entity.x -= movement.0;
+                    alien.entity.y -= movement.1;
+                    dive.offset = (dive.offset.0 - movement.0, dive.offset.1 - movement.1);
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::AlienVariant;
+    use crate::world::FIXED_TIMESTEP;
+
+    // Center x of the shot once it falls to the height of the cannon
+    fn landing_x(kind: ShotKind, alien_x: f64, context: &BehaviorContext) -> f64 {
+        let mut alien = Alien::new(alien_x, 64.0, AlienSpecies::Soldier, AlienVariant::Default, (0, 0));
+        let mut shot = SoldierBehavior.shoot(&mut alien, context, kind, 180.0);
+
+        while shot.entity.y < context.cannon_y {
+            shot.advance(FIXED_TIMESTEP);
+        }
+        shot.entity.x + (shot.entity.width / 2.0)
+    }
+
+    fn context(cannon_x: f64) -> BehaviorContext {
+        BehaviorContext {
+            cannon_x,
+            cannon_y: 380.0,
+            world_height: 480.0,
+            diving_count: 0,
+        }
+    }
+
+    #[test]
+    fn soldier_shots_land_on_the_cannon() {
+        for kind in [ShotKind::Plunger, ShotKind::Straight] {
+            // Within the reach of SOLDIER_MAX_AIM
+            for cannon_x in [260.0, 300.0, 350.0] {
+                let x = landing_x(kind, 300.0, &context(cannon_x));
+                assert!((x - cannon_x).abs() < 4.0, "{:?} shot landed at {} instead of {}", kind, x, cannon_x);
+            }
+        }
+    }
+
+    #[test]
+    fn soldier_aim_is_limited() {
+        let mut alien = Alien::new(300.0, 64.0, AlienSpecies::Soldier, AlienVariant::Default, (0, 0));
+        let shot = SoldierBehavior.shoot(&mut alien, &context(0.0), ShotKind::Plunger, 180.0);
+        assert_eq!(shot.speed_x, -shot.entity.movement_speed * SOLDIER_MAX_AIM);
+    }
+
+    #[test]
+    fn soldier_rolling_shots_are_not_aimed() {
+        let mut alien = Alien::new(300.0, 64.0, AlienSpecies::Soldier, AlienVariant::Default, (0, 0));
+        let shot = SoldierBehavior.shoot(&mut alien, &context(100.0), ShotKind::Rolling, 180.0);
+        assert_eq!(shot.speed_x, 0.0);
+    }
+}
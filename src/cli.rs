@@ -0,0 +1,170 @@
+use crate::error::GameError;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: invaders [OPTIONS]
+
+Options:
+  --frontend <NAME>   window, terminal or headless (default: window)
+  --seed <N>          seed of the random generator (default: random)
+  --config <FILE>     TOML file with the window and balance settings
+  --level <FILE>      play this level file instead of the campaign, repeat for more levels
+  --replay <FILE>     play back a recorded match
+  --record <FILE>     save the inputs of the last match to a replay file
+  --scale <FACTOR>    window scale factor (default: 1)
+  -h, --help          print this help
+
+Environment:
+  INVADERS_ASSETS_DIR  directory with images that replace the embedded ones in the window";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Frontend {
+    Window,
+    Terminal,
+    // Runs the simulation without drawing or reading the keyboard, until the match is over
+    Headless,
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub frontend: Frontend,
+    pub seed: Option<u64>,
+    pub config: Option<PathBuf>,
+    pub levels: Vec<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub scale: f64,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            frontend: Frontend::Window,
+            seed: None,
+            config: None,
+            levels: vec![],
+            replay: None,
+            record: None,
+            scale: 1.0,
+            help: false,
+        }
+    }
+}
+
+// Parse the command-line arguments, without the program name
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, GameError> {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        // Both "--option value" and "--option=value" are accepted
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (String::from(name), Some(String::from(value))),
+            _ => (arg.clone(), None),
+        };
+
+        match name.as_str() {
+            "-h" | "--help" => options.help = true,
+            "--frontend" => {
+                let value = option_value(&name, inline_value, &mut args)?;
+                options.frontend = match value.as_str() {
+                    "window" => Frontend::Window,
+                    "terminal" => Frontend::Terminal,
+                    "headless" => Frontend::Headless,
+                    _ => return Err(GameError::InvalidArgument(format!("unknown frontend {}, expected window, terminal or headless", value))),
+                };
+            },
+            "--seed" => {
+                let value = option_value(&name, inline_value, &mut args)?;
+                let seed = value.parse::<u64>()
+                    .map_err(|_| GameError::InvalidArgument(format!("invalid seed {}", value)))?;
+                options.seed = Some(seed);
+            },
+            "--config" => options.config = Some(PathBuf::from(option_value(&name, inline_value, &mut args)?)),
+            "--level" => options.levels.push(PathBuf::from(option_value(&name, inline_value, &mut args)?)),
+            "--replay" => options.replay = Some(PathBuf::from(option_value(&name, inline_value, &mut args)?)),
+            "--record" => options.record = Some(PathBuf::from(option_value(&name, inline_value, &mut args)?)),
+            "--scale" => {
+                let value = option_value(&name, inline_value, &mut args)?;
+                let scale = value.parse::<f64>()
+                    .ok()
+                    .filter(|scale| scale.is_finite() && *scale > 0.0)
+                    .ok_or_else(|| GameError::InvalidArgument(format!("invalid scale {}, expected a positive number", value)))?;
+                options.scale = scale;
+            },
+            _ => return Err(GameError::InvalidArgument(format!("unknown option {}", arg))),
+        }
+    }
+
+    Ok(options)
+}
+
+fn option_value<I: Iterator<Item = String>>(name: &str, inline_value: Option<String>, args: &mut I) -> Result<String, GameError> {
+    inline_value
+        .or_else(|| args.next())
+        .ok_or_else(|| GameError::InvalidArgument(format!("missing value for {}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, GameError> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn defaults_without_arguments() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(options.frontend, Frontend::Window);
+        assert_eq!(options.seed, None);
+        assert_eq!(options.config, None);
+        assert!(options.levels.is_empty());
+        assert_eq!(options.replay, None);
+        assert_eq!(options.record, None);
+        assert_eq!(options.scale, 1.0);
+        assert!(!options.help);
+    }
+
+    #[test]
+    fn separate_and_inline_values() {
+        let options = parse(&[
+            "--frontend", "terminal", "--seed=42", "--config", "invaders.toml",
+            "--level=a.level", "--level", "b.level", "--replay", "in.invr", "--record=out.invr", "--scale", "2.5",
+        ]).unwrap();
+
+        assert_eq!(options.frontend, Frontend::Terminal);
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.config, Some(PathBuf::from("invaders.toml")));
+        assert_eq!(options.levels, vec![PathBuf::from("a.level"), PathBuf::from("b.level")]);
+        assert_eq!(options.replay, Some(PathBuf::from("in.invr")));
+        assert_eq!(options.record, Some(PathBuf::from("out.invr")));
+        assert_eq!(options.scale, 2.5);
+    }
+
+    #[test]
+    fn help_flags() {
+        assert!(parse(&["-h"]).unwrap().help);
+        assert!(parse(&["--help"]).unwrap().help);
+        assert!(parse(&["--frontend=headless", "--help"]).unwrap().help);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(parse(&["--fast"]).unwrap_err().to_string().contains("unknown option --fast"));
+        assert!(parse(&["--fast=yes"]).unwrap_err().to_string().contains("unknown option --fast=yes"));
+        assert!(parse(&["play"]).unwrap_err().to_string().contains("unknown option play"));
+        assert!(parse(&["--frontend", "web"]).unwrap_err().to_string().contains("unknown frontend web"));
+        assert!(parse(&["--seed", "-1"]).unwrap_err().to_string().contains("invalid seed -1"));
+        assert!(parse(&["--scale=0"]).unwrap_err().to_string().contains("invalid scale 0"));
+        assert!(parse(&["--scale", "inf"]).unwrap_err().to_string().contains("invalid scale inf"));
+    }
+
+    #[test]
+    fn rejects_missing_values() {
+        for option in ["--frontend", "--seed", "--config", "--level", "--replay", "--record", "--scale"] {
+            let message = parse(&[option]).unwrap_err().to_string();
+            assert!(message.contains(&format!("missing value for {}", option)), "{}", message);
+        }
+    }
+}
@@ -0,0 +1,339 @@
+extern crate serde;
+extern crate toml;
+
+use crate::error::GameError;
+use crate::formation::{max_columns, max_rows, min_width, CLASSIC_COLUMNS, CLASSIC_ROWS};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+// Balance and window settings, every missing value falls back to its default
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub cannon: CannonConfig,
+    pub aliens: AliensConfig,
+    pub waves: WavesConfig,
+    pub ufo: UfoConfig,
+    pub barricades: BarricadesConfig,
+    pub firing: FiringConfig,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    pub title: String,
+}
+
+// Speeds are in pixels per second and times in seconds
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CannonConfig {
+    pub lives: i32,
+    pub movement_speed: f64,
+    // Distance between the top of the cannon and the bottom of the window
+    pub y_offset: f64,
+    pub shot_speed: f64,
+    pub explosion_time: f64,
+    pub invulnerable_time: f64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AliensConfig {
+    pub movement_speed: f64,
+    // Speed gained by the formation for each alien killed
+    pub kill_speed_increase: f64,
+    // The formation goes down this fraction of an alien height at each side of the window
+    pub height_modifier: f64,
+    pub shot_speed: f64,
+    pub shot_interval: f64,
+    // Arcade march: a single alien moves `march_step` pixels per step instead of the formation gliding,
+    // so it speeds up by itself as aliens die, `movement_speed` only gives the direction and kills add no speed
+    pub step_march: bool,
+    pub march_step: f64,
+}
+
+// Each wave starts lower, faster and firing more often than the previous one
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WavesConfig {
+    pub drop: f64,
+    pub max_drops: u32,
+    pub speed_increase: f64,
+    pub shot_interval_factor: f64,
+    pub min_shot_interval: f64,
+    // Stronger alien variants on each wave of the classic formation, or on each loop of the levels
+    pub tougher_variants: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UfoConfig {
+    pub min_interval: f64,
+    pub max_interval: f64,
+    pub movement_speed: f64,
+    pub y: f64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BarricadesConfig {
+    pub count: u32,
+    // Distance between the top of the barricades and the top of the cannon
+    pub height_from_cannon: f64,
+}
+
+// How the aliens pick who fires, the fire rate is set by the aliens, waves and levels
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FiringConfig {
+    // Alien shots falling at the same time
+    pub max_shots: u32,
+    // Columns up to `player_column_range` pixels from the cannon are up to this much likelier to fire
+    pub player_column_weight: f64,
+    pub player_column_range: f64,
+    // Every this many shots one is a rolling shot aimed at the cannon, 0 for none
+    pub rolling_every: u32,
+}
+
+impl Default for WindowConfig {
+    fn default() -> WindowConfig {
+        WindowConfig {
+            width: 640,
+            height: 480,
+            title: String::from("Espace Invaders"),
+        }
+    }
+}
+
+impl Default for CannonConfig {
+    fn default() -> CannonConfig {
+        CannonConfig {
+            lives: 5,
+            movement_speed: 240.0,
+            y_offset: 100.0,
+            shot_speed: 300.0,
+            explosion_time: 1.5,
+            invulnerable_time: 2.0,
+        }
+    }
+}
+
+impl Default for AliensConfig {
+    fn default() -> AliensConfig {
+        AliensConfig {
+            movement_speed: 120.0,
+            kill_speed_increase: 12.0,
+            height_modifier: 0.5,
+            shot_speed: 180.0,
+            shot_interval: 1.0,
+            step_march: false,
+            march_step: 8.0,
+        }
+    }
+}
+
+impl Default for WavesConfig {
+    fn default() -> WavesConfig {
+        WavesConfig {
+            drop: 16.0,
+            max_drops: 6,
+            speed_increase: 30.0,
+            shot_interval_factor: 0.85,
+            min_shot_interval: 0.3,
+            tougher_variants: true,
+        }
+    }
+}
+
+impl Default for UfoConfig {
+    fn default() -> UfoConfig {
+        UfoConfig {
+            min_interval: 20.0,
+            max_interval: 30.0,
+            movement_speed: 90.0,
+            y: 30.0,
+        }
+    }
+}
+
+impl Default for BarricadesConfig {
+    fn default() -> BarricadesConfig {
+        BarricadesConfig {
+            count: 4,
+            height_from_cannon: 96.0,
+        }
+    }
+}
+
+impl Default for FiringConfig {
+    fn default() -> FiringConfig {
+        FiringConfig {
+            max_shots: 3,
+            player_column_weight: 4.0,
+            player_column_range: 96.0,
+            rolling_every: 3,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, GameError> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| GameError::ConfigParse(format!("{}: {}", path.display(), error)))?;
+        let config: Config = toml::from_str(&text)
+            .map_err(|error| GameError::ConfigParse(format!("{}: {}", path.display(), error)))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Top of the barricades, the formations must stay above it
+    pub fn get_barricades_y(&self) -> f64 {
+        self.window.height as f64 - self.cannon.y_offset - self.barricades.height_from_cannon
+    }
+
+    pub fn validate(&self) -> Result<(), GameError> {
+        if self.window.width < 320 || self.window.height < 240 {
+            return Err(invalid("window", "must be at least 320x240"));
+        }
+        if max_columns(self.window.width) < CLASSIC_COLUMNS {
+            let message = format!("must be at least {} to fit the {} columns of the formation", min_width(CLASSIC_COLUMNS), CLASSIC_COLUMNS);
+            return Err(invalid("window.width", &message));
+        }
+        if self.firing.max_shots < 1 {
+            return Err(invalid("firing.max_shots", "must be at least 1"));
+        }
+        if self.cannon.lives < 1 {
+            return Err(invalid("cannon.lives", "must be at least 1"));
+        }
+        if !(self.cannon.y_offset >= 32.0 && self.cannon.y_offset <= self.window.height as f64) {
+            return Err(invalid("cannon.y_offset", "must keep the cannon inside the window"));
+        }
+
+        let positive_values = [
+            ("cannon.movement_speed", self.cannon.movement_speed),
+            ("cannon.shot_speed", self.cannon.shot_speed),
+            ("aliens.movement_speed", self.aliens.movement_speed),
+            ("aliens.shot_speed", self.aliens.shot_speed),
+            ("aliens.shot_interval", self.aliens.shot_interval),
+            ("aliens.march_step", self.aliens.march_step),
+            ("waves.shot_interval_factor", self.waves.shot_interval_factor),
+            ("waves.min_shot_interval", self.waves.min_shot_interval),
+            ("ufo.min_interval", self.ufo.min_interval),
+            ("ufo.max_interval", self.ufo.max_interval),
+            ("ufo.movement_speed", self.ufo.movement_speed),
+            ("firing.player_column_range", self.firing.player_column_range),
+        ];
+        for (name, value) in positive_values {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(invalid(name, "must be a positive number"));
+            }
+        }
+
+        let non_negative_values = [
+            ("cannon.explosion_time", self.cannon.explosion_time),
+            ("cannon.invulnerable_time", self.cannon.invulnerable_time),
+            ("aliens.kill_speed_increase", self.aliens.kill_speed_increase),
+            ("aliens.height_modifier", self.aliens.height_modifier),
+            ("waves.drop", self.waves.drop),
+            ("waves.speed_increase", self.waves.speed_increase),
+            ("ufo.y", self.ufo.y),
+            ("barricades.height_from_cannon", self.barricades.height_from_cannon),
+            ("firing.player_column_weight", self.firing.player_column_weight),
+        ];
+        for (name, value) in non_negative_values {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(invalid(name, "must not be negative"));
+            }
+        }
+
+        if max_rows(self.get_barricades_y()) < CLASSIC_ROWS {
+            let message = format!("must leave room above the barricades for the {} rows of the formation", CLASSIC_ROWS);
+            return Err(invalid("barricades.height_from_cannon", &message));
+        }
+
+        if self.ufo.max_interval <= self.ufo.min_interval {
+            return Err(invalid("ufo.max_interval", "must be greater than ufo.min_interval"));
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid(name: &str, message: &str) -> GameError {
+    GameError::ConfigParse(format!("{} {}", name, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Makes a single setting of the default config invalid
+    type Change = fn(&mut Config);
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn sample_config_is_the_default_one() {
+        let config: Config = toml::from_str(include_str!("../invaders.toml")).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(format!("{:?}", config), format!("{:?}", Config::default()));
+    }
+
+    #[test]
+    fn rejects_each_invalid_setting() {
+        let cases: Vec<(&str, Change)> = vec![
+            ("window", |config| config.window.width = 100),
+            ("window.width", |config| config.window.width = 400),
+            ("window", |config| config.window.height = 100),
+            ("firing.max_shots", |config| config.firing.max_shots = 0),
+            ("cannon.lives", |config| config.cannon.lives = 0),
+            ("cannon.y_offset", |config| config.cannon.y_offset = 8.0),
+            ("cannon.y_offset", |config| config.cannon.y_offset = 1000.0),
+            ("cannon.y_offset", |config| config.cannon.y_offset = f64::NAN),
+            ("cannon.movement_speed", |config| config.cannon.movement_speed = 0.0),
+            ("cannon.shot_speed", |config| config.cannon.shot_speed = -1.0),
+            ("aliens.movement_speed", |config| config.aliens.movement_speed = f64::INFINITY),
+            ("aliens.shot_speed", |config| config.aliens.shot_speed = 0.0),
+            ("aliens.shot_interval", |config| config.aliens.shot_interval = 0.0),
+            ("aliens.march_step", |config| config.aliens.march_step = 0.0),
+            ("waves.shot_interval_factor", |config| config.waves.shot_interval_factor = 0.0),
+            ("waves.min_shot_interval", |config| config.waves.min_shot_interval = f64::NAN),
+            ("ufo.min_interval", |config| config.ufo.min_interval = 0.0),
+            ("ufo.max_interval", |config| config.ufo.max_interval = f64::INFINITY),
+            ("ufo.movement_speed", |config| config.ufo.movement_speed = 0.0),
+            ("firing.player_column_range", |config| config.firing.player_column_range = 0.0),
+            ("cannon.explosion_time", |config| config.cannon.explosion_time = -1.0),
+            ("cannon.invulnerable_time", |config| config.cannon.invulnerable_time = f64::INFINITY),
+            ("aliens.kill_speed_increase", |config| config.aliens.kill_speed_increase = -1.0),
+            ("aliens.height_modifier", |config| config.aliens.height_modifier = -0.5),
+            ("waves.drop", |config| config.waves.drop = -1.0),
+            ("waves.speed_increase", |config| config.waves.speed_increase = f64::NAN),
+            ("ufo.y", |config| config.ufo.y = -1.0),
+            ("barricades.height_from_cannon", |config| config.barricades.height_from_cannon = -1.0),
+            ("firing.player_column_weight", |config| config.firing.player_column_weight = -1.0),
+            ("ufo.max_interval", |config| config.ufo.max_interval = config.ufo.min_interval),
+            ("barricades.height_from_cannon", |config| config.barricades.height_from_cannon = 200.0),
+        ];
+
+        for (name, change) in cases {
+            let mut config = Config::default();
+            change(&mut config);
+            let message = config.validate().unwrap_err().to_string();
+            assert!(message.contains(name), "expected an error about {}, got {}", name, message);
+        }
+    }
+
+    #[test]
+    fn load_reports_unknown_keys() {
+        let error = toml::from_str::<Config>("[cannon]\nlifes = 3\n").unwrap_err();
+        assert!(error.to_string().contains("lifes"));
+    }
+}
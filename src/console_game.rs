@@ -1,63 +1,394 @@
+extern crate crossterm;
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use crate::entities::{AlienSpecies, AlienVariant, Barricade, Cannon, Entity, ShotKind, Sprite, BARRICADE_CELLS};
+use crate::error::GameError;
+use crate::hud::Hud;
+use crate::sound::Sound;
+use crate::state::{GameState, Session, StateInput};
+use crate::world::InputState;
+use std::io::{stdout, Stdout, Write};
 use std::ops::Mul;
 use std::time::{Duration, Instant};
 use std::thread;
 
+// Size of the terminal grid the world is drawn on
+const COLUMNS: usize = 80;
+const ROWS: usize = 30;
+
+// Terminals without the keyboard enhancement only send key presses,
+// so there a direction is held this long after its last press or repeat
+const KEY_HOLD_TIME: Duration = Duration::from_millis(150);
+
+type Cell = (char, Color);
+
 pub struct Game {
     // Renderer attributes
     start_time: Instant,
     frame_count: u32,
     frame_time: Duration,
 
-
+    session: Session,
+    sound: Sound,
+    input: InputState,
+    left_held_until: Option<Instant>,
+    right_held_until: Option<Instant>,
+    // The terminal reports key releases, a direction is then held until it is released
+    key_releases: bool,
+    quit: bool,
 }
 
-impl Default for Game {
-    fn default() -> Self {
+impl Game {
+    pub fn new(session: Session) -> Game {
         Game {
             start_time: Instant::now(),
             frame_count: 0,
-            frame_time: Duration::from_secs_f64(1.0 / 60.0) // 60 fps
+            frame_time: Duration::from_secs_f64(1.0 / 60.0), // 60 fps
+
+            session,
+            sound: Sound::open(),
+            input: InputState::default(),
+            left_held_until: None,
+            right_held_until: None,
+            key_releases: false,
+            quit: false,
         }
     }
-}
 
-impl Game {
+    pub fn get_session(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn start_game(&mut self) -> Result<(), GameError> {
+        let mut out = stdout();
+        terminal::enable_raw_mode().map_err(terminal_error)?;
+        execute!(out, EnterAlternateScreen, Hide, Clear(ClearType::All)).map_err(terminal_error)?;
+
+        // Ask for key release events where the terminal supports it
+        self.key_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if self.key_releases {
+            let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES | KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+            execute!(out, PushKeyboardEnhancementFlags(flags)).map_err(terminal_error)?;
+        }
+
+        let result = self.run(&mut out);
+
+        // Give the terminal back even when the game failed
+        if self.key_releases {
+            execute!(out, PopKeyboardEnhancementFlags).map_err(terminal_error)?;
+        }
+        execute!(out, ResetColor, Show, LeaveAlternateScreen).map_err(terminal_error)?;
+        terminal::disable_raw_mode().map_err(terminal_error)?;
+        result
+    }
+
+    fn run(&mut self, out: &mut Stdout) -> Result<(), GameError> {
+        let mut last_frame_time = Instant::now();
+        self.start_time = last_frame_time;
+        self.frame_count = 0;
 
-    pub fn start_game(&mut self) {
         //Main game loop
-        loop {
+        while !self.quit {
+            self.handle_keyboard()?;
+
+            // Advance the simulation
+            let now = Instant::now();
+            let held = |until: Option<Instant>| until.is_some_and(|until| self.key_releases || now < until);
+            self.input.left = held(self.left_held_until);
+            self.input.right = held(self.right_held_until);
+            self.session.advance(self.input, now.duration_since(last_frame_time).as_secs_f64())?;
+            self.sound.play_events(self.session.get_events());
+            self.input.fire = false;
+            last_frame_time = now;
+
             // Render the frame
-            render_frame();
+            self.render_frame(out)?;
 
             // Update the frame count
             self.frame_count += 1;
 
             // Wait for the next frame
             let elapsed_time = self.start_time.elapsed();
-            let target_time = self.frame_time.mul(self.frame_count as u32);
+            let target_time = self.frame_time.mul(self.frame_count);
             if elapsed_time < target_time {
                 thread::sleep(target_time - elapsed_time);
             }
+        }
+
+        Ok(())
+    }
 
-            // Calculate the frame rate
-            if let Some(frame_rate) = calculate_frame_rate(self.frame_count, elapsed_time) {
-                println!("Frame rate: {:.2} fps", frame_rate);
+    fn handle_keyboard(&mut self) -> Result<(), GameError> {
+        while event::poll(Duration::ZERO).map_err(terminal_error)? {
+            let key = match event::read().map_err(terminal_error)? {
+                Event::Key(key) => key,
+                _ => continue,
+            };
+
+            let hold_until = Some(Instant::now() + KEY_HOLD_TIME);
+            if key.kind == KeyEventKind::Release {
+                match key.code {
+                    KeyCode::Left => self.left_held_until = None,
+                    KeyCode::Right => self.right_held_until = None,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Esc => self.quit = true,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+                KeyCode::Left => {
+                    self.left_held_until = hold_until;
+                    self.right_held_until = None;
+                },
+                KeyCode::Right => {
+                    self.right_held_until = hold_until;
+                    self.left_held_until = None;
+                },
+                KeyCode::Char(' ') => {
+                    self.input.fire = self.session.state == GameState::Playing;
+                    self.session.handle_input(StateInput::Confirm)?;
+                },
+                KeyCode::Enter => self.session.handle_input(StateInput::Confirm)?,
+                KeyCode::Backspace => self.session.handle_input(StateInput::Erase)?,
+                KeyCode::Char(letter) if letter.is_ascii_alphanumeric() => {
+                    // Letters and digits, R restarts and P pauses
+                    self.session.handle_input(StateInput::Letter(letter.to_ascii_uppercase()))?;
+                },
+                _ => {}
             }
         }
 
+        Ok(())
+    }
+
+    fn render_frame(&self, out: &mut Stdout) -> Result<(), GameError> {
+        let mut screen = Screen::new(self.session.world.width as f64, self.session.world.height as f64);
+
+        if self.session.state != GameState::Title {
+            screen.draw_world(&self.session);
+            screen.draw_hud(&Hud::new(&self.session.world));
+        }
+        screen.draw_state(&self.session);
+
+        screen.flush(out)
     }
 }
 
-fn render_frame() {
-    // unimplemented!("Render the frame");
+// Character grid with the world scaled down to it
+struct Screen {
+    cells: Vec<Vec<Cell>>,
+    scale: (f64, f64),
 }
 
-fn calculate_frame_rate(frame_count: u32, elapsed_time: Duration) -> Option<f64> {
-    if frame_count > 1 {
-        let prev_time = elapsed_time.checked_sub(Duration::from_secs(1))?;
-        let frame_rate = frame_count as f64 / prev_time.as_secs_f64();
-        Some(frame_rate)
-    } else {
-        None
+impl Screen {
+    fn new(width: f64, height: f64) -> Screen {
+        Screen {
+            cells: vec![vec![(' ', Color::Reset); COLUMNS]; ROWS],
+            scale: (width / COLUMNS as f64, height / ROWS as f64),
+        }
+    }
+
+    fn put(&mut self, col: i32, row: i32, character: char, color: Color) {
+        if col >= 0 && row >= 0 && (col as usize) < COLUMNS && (row as usize) < ROWS {
+            self.cells[row as usize][col as usize] = (character, color);
+        }
+    }
+
+    fn put_text(&mut self, col: i32, row: i32, text: &str, color: Color) {
+        for (index, character) in text.chars().enumerate() {
+            self.put(col + index as i32, row, character, color);
+        }
+    }
+
+    fn put_centered_text(&mut self, row: i32, text: &str, color: Color) {
+        let col = (COLUMNS as i32 - text.chars().count() as i32) / 2;
+        self.put_text(col, row, text, color);
+    }
+
+    fn to_cell(&self, x: f64, y: f64) -> (i32, i32) {
+        ((x / self.scale.0).floor() as i32, (y / self.scale.1).floor() as i32)
+    }
+
+    // Draw the lines of a small character sprite on the top left of the entity
+    fn put_sprite(&mut self, entity: &Entity, lines: &[&str], color: Color) {
+        let (col, row) = self.to_cell(entity.x, entity.y);
+        for (index, line) in lines.iter().enumerate() {
+            self.put_text(col, row + index as i32, line, color);
+        }
     }
-}
\ No newline at end of file
+
+    fn draw_world(&mut self, session: &Session) {
+        let world = &session.world;
+
+        self.draw_cannon(&world.cannon);
+
+        for alien in world.aliens.iter().flat_map(|row| row.iter()) {
+            let (species, variant) = (alien.get_species(), alien.get_variant());
+            let lines: &[&str] = match (species, alien.get_frame()) {
+                (AlienSpecies::Squid, 0) => &["/oo\\", "<  >"],
+                (AlienSpecies::Squid, _) => &["/oo\\", " >< "],
+                (AlienSpecies::Soldier, 0) => &["{@@}", "/\"\"\\"],
+                (AlienSpecies::Soldier, _) => &["{@@}", "\\\"\"/"],
+                (AlienSpecies::Bug, 0) => &["(--)", "/\\/\\"],
+                (AlienSpecies::Bug, _) => &["(--)", "\\/\\/"],
+            };
+            // Damaged aliens are drawn darker, and red right after a hit
+            let color = match (variant, alien.is_damaged()) {
+                _ if alien.is_flashing() => Color::Red,
+                (AlienVariant::Default, false) => Color::Green,
+                (AlienVariant::Default, true) => Color::DarkGreen,
+                (AlienVariant::White, false) => Color::White,
+                (AlienVariant::White, true) => Color::Grey,
+                (AlienVariant::Pink, false) => Color::Magenta,
+                (AlienVariant::Pink, true) => Color::DarkMagenta,
+                (AlienVariant::Blue, false) => Color::Blue,
+                (AlienVariant::Blue, true) => Color::DarkBlue,
+            };
+            self.put_sprite(&alien.entity, lines, color);
+        }
+
+        if let Some(ufo) = world.ufo.as_ref() {
+            self.put_sprite(&ufo.entity, &["<OO>"], Color::Red);
+        }
+        if let Some(popup) = world.ufo_score_popup.as_ref() {
+            let (col, row) = self.to_cell(popup.x, popup.y);
+            let text = popup.score.to_string();
+            self.put_text(col - (text.len() as i32 / 2), row, &text, Color::Red);
+        }
+
+        for barricade in world.barricades.iter() {
+            self.draw_barricade(barricade);
+        }
+
+        for shot in world.cannon_shots.iter() {
+            self.put_sprite(&shot.entity, &["|"], Color::Yellow);
+        }
+        for shot in world.alien_shots.iter() {
+            let character = match shot.entity.sprite {
+                Sprite::AlienShot(ShotKind::Squiggly) => "z",
+                Sprite::AlienShot(ShotKind::Plunger) => "T",
+                Sprite::AlienShot(ShotKind::Rolling) => "+",
+                _ => "!",
+            };
+            self.put_sprite(&shot.entity, &[character], Color::Red);
+        }
+    }
+
+    fn draw_cannon(&mut self, cannon: &Cannon) {
+        if cannon.is_exploding() {
+            let frame = (cannon.exploding_time * 10.0) as usize;
+            let lines: &[&str] = if frame.is_multiple_of(2) { &["    ", "*#%*"] } else { &["    ", "%*#%"] };
+            self.put_sprite(&cannon.entity, lines, Color::DarkYellow);
+            return;
+        }
+
+        if cannon.is_invulnerable() && (cannon.invulnerable_time * 10.0) as i32 % 2 == 0 {
+            return;
+        }
+
+        self.put_sprite(&cannon.entity, &[" /\\ ", "/##\\"], Color::Green);
+    }
+
+    // Each character covers a block of barricade cells, drawn by how much of it is standing
+    fn draw_barricade(&mut self, barricade: &Barricade) {
+        let (first_col, first_row) = self.to_cell(barricade.entity.x, barricade.entity.y);
+        let cell_size = barricade.get_cell_size();
+        let cells_per_col = ((self.scale.0 / cell_size.0).round() as usize).max(1);
+        let cells_per_row = ((self.scale.1 / cell_size.1).round() as usize).max(1);
+
+        for block_row in 0..(BARRICADE_CELLS / cells_per_row) {
+            for block_col in 0..(BARRICADE_CELLS / cells_per_col) {
+                let standing = (0..cells_per_row)
+                    .flat_map(|row| (0..cells_per_col).map(move |col| (row, col)))
+                    .filter(|(row, col)| barricade.cells[block_row * cells_per_row + row][block_col * cells_per_col + col])
+                    .count();
+
+                let character = match standing * 4 / (cells_per_row * cells_per_col) {
+                    0 if standing == 0 => continue,
+                    0 => '.',
+                    1 => ':',
+                    2 => '+',
+                    _ => '#',
+                };
+                self.put(first_col + block_col as i32, first_row + block_row as i32, character, Color::Green);
+            }
+        }
+    }
+
+    fn draw_hud(&mut self, hud: &Hud) {
+        let high_score_text = hud.high_score_text();
+        let wave_text = hud.wave_text();
+
+        self.put_text(0, 0, &hud.score_text(), Color::White);
+        self.put_centered_text(0, &high_score_text, Color::White);
+        self.put_text(COLUMNS as i32 - wave_text.len() as i32, 0, &wave_text, Color::White);
+
+        let level_text = hud.level_text();
+        self.put_text(COLUMNS as i32 - level_text.chars().count() as i32, 1, &level_text, Color::White);
+
+        let lives = format!("{} {}", hud.lives_text(), "/^\\ ".repeat(hud.lives as usize));
+        self.put_text(0, ROWS as i32 - 1, &lives, Color::Green);
+    }
+
+    // Title, pause and game over screens drawn over the world
+    fn draw_state(&mut self, session: &Session) {
+        let center = ROWS as i32 / 2;
+
+        match &session.state {
+            GameState::Title => {
+                self.put_centered_text(center - 4, "S P A C E   I N V A D E R S", Color::Green);
+                self.put_centered_text(center, "PRESS ENTER TO PLAY", Color::White);
+                self.put_centered_text(center + 2, "ARROWS MOVE - SPACE FIRES - P PAUSES - R RESTARTS - ESC QUITS", Color::White);
+            },
+            GameState::Paused => {
+                self.put_centered_text(center, "PAUSED", Color::White);
+            },
+            GameState::GameOver => {
+                self.put_centered_text(center - 6, "GAME OVER", Color::Red);
+                for (index, high_score) in session.high_scores.iter().enumerate() {
+                    let text = format!("{}. {:<3} {:05}", index + 1, high_score.name, high_score.score);
+                    self.put_centered_text(center - 4 + index as i32, &text, Color::White);
+                }
+                self.put_centered_text(center + 3, "PRESS ENTER TO PLAY AGAIN", Color::White);
+            },
+            GameState::HighScoreEntry { name } => {
+                self.put_centered_text(center - 2, "NEW HIGH SCORE!", Color::Yellow);
+                self.put_centered_text(center, &format!("NAME: {:-<3}", name), Color::White);
+            },
+            GameState::Playing | GameState::LifeLost => {},
+        }
+    }
+
+    fn flush(&self, out: &mut Stdout) -> Result<(), GameError> {
+        let (terminal_columns, terminal_rows) = terminal::size().map_err(terminal_error)?;
+        let columns = COLUMNS.min(terminal_columns as usize);
+        let rows = ROWS.min(terminal_rows as usize);
+
+        for (row, cells) in self.cells.iter().enumerate().take(rows) {
+            queue!(out, MoveTo(0, row as u16)).map_err(terminal_error)?;
+
+            let mut color = None;
+            for (character, cell_color) in cells.iter().take(columns) {
+                if color != Some(*cell_color) {
+                    queue!(out, SetForegroundColor(*cell_color)).map_err(terminal_error)?;
+                    color = Some(*cell_color);
+                }
+                queue!(out, Print(character)).map_err(terminal_error)?;
+            }
+        }
+
+        out.flush().map_err(terminal_error)
+    }
+}
+
+fn terminal_error(error: std::io::Error) -> GameError {
+    GameError::Terminal(error.to_string())
+}
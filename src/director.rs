@@ -0,0 +1,192 @@
+use crate::config::FiringConfig;
+use crate::entities::{Alien, ShotKind};
+use std::collections::BTreeMap;
+use rand::rngs::StdRng;
+use rand::Rng;
+
+// Alien (row, index) that fires, with the kind of its shot when it is not up to the species
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FiringOrder {
+    pub alien: (usize, usize),
+    pub kind: Option<ShotKind>,
+}
+
+// Decides when the aliens fire and which one does
+#[derive(Clone, Debug)]
+pub struct FiringDirector {
+    // Seconds between shots and kind of every shot of the current wave
+    pub shot_interval: f64,
+    pub wave_shot_kind: Option<ShotKind>,
+
+    config: FiringConfig,
+    elapsed_time: f64,
+    shot_count: u32,
+}
+
+impl FiringDirector {
+    pub fn new(config: FiringConfig, shot_interval: f64) -> FiringDirector {
+        FiringDirector {
+            shot_interval,
+            wave_shot_kind: None,
+
+            config,
+            elapsed_time: 0.0,
+            shot_count: 0,
+        }
+    }
+
+    pub fn start_wave(&mut self, shot_interval: f64, wave_shot_kind: Option<ShotKind>) {
+        self.shot_interval = shot_interval;
+        self.wave_shot_kind = wave_shot_kind;
+        self.elapsed_time = 0.0;
+        self.shot_count = 0;
+    }
+
+    // Called every step, returns the alien that fires now if any, `dt` is the elapsed time in seconds
+    pub fn update(&mut self, aliens: &[Vec<Alien>], cannon_x: f64, shots_in_flight: usize, rng: &mut StdRng, dt: f64) -> Option<FiringOrder> {
+        self.elapsed_time += dt;
+
+        // The next shot waits for the interval and for a free slot
+        if self.elapsed_time < self.shot_interval || shots_in_flight >= self.config.max_shots as usize {
+            return None;
+        }
+
+        let columns = lowest_aliens(aliens);
+        if columns.is_empty() {
+            return None;
+        }
+
+        // Every few shots, or every shot when the wave says so, a rolling shot falls from the column nearest the cannon
+        self.shot_count += 1;
+        let rolling = self.wave_shot_kind == Some(ShotKind::Rolling)
+            || (self.config.rolling_every > 0 && self.shot_count.is_multiple_of(self.config.rolling_every));
+
+        let weights: Vec<(f64, (usize, usize))> = columns.values()
+            .map(|alien_pos| {
+                let alien = &aliens[alien_pos.0][alien_pos.1];
+                let distance = (alien.entity.x + (alien.entity.width / 2.0) - cannon_x).abs();
+                (distance, *alien_pos)
+            })
+            .collect();
+
+        let alien = if rolling {
+            weights.iter()
+                .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+                .map(|weight| weight.1)?
+        } else {
+            self.weighted_choice(&weights, rng)
+        };
+
+        self.elapsed_time = 0.0;
+        Some(FiringOrder {
+            alien,
+            kind: if rolling { Some(ShotKind::Rolling) } else { self.wave_shot_kind },
+        })
+    }
+
+    // Random column, the closer to the cannon the likelier, `distances` is not empty
+    fn weighted_choice(&self, distances: &[(f64, (usize, usize))], rng: &mut StdRng) -> (usize, usize) {
+        let weight = |distance: f64| {
+            let closeness = (1.0 - (distance / self.config.player_column_range)).max(0.0);
+            1.0 + (self.config.player_column_weight * closeness)
+        };
+
+        let total: f64 = distances.iter().map(|(distance, _)| weight(*distance)).sum();
+        let mut choice = rng.gen_range(0.0..total);
+        for (distance, alien_pos) in distances.iter() {
+            choice -= weight(*distance);
+            if choice < 0.0 {
+                return *alien_pos;
+            }
+        }
+
+        distances[distances.len() - 1].1
+    }
+}
+
+// Lowest alive alien (row, index) of each formation column, aliens out of the formation can not fire
+pub fn lowest_aliens(aliens: &[Vec<Alien>]) -> BTreeMap<u32, (usize, usize)> {
+    let mut lowest: BTreeMap<u32, (usize, usize)> = BTreeMap::new();
+
+    for (row_index, row) in aliens.iter().enumerate() {
+        for (index, alien) in row.iter().enumerate() {
+            if alien.is_diving() || alien.is_dead() {
+                continue;
+            }
+
+            let col = alien.position.1;
+            let is_lower = match lowest.get(&col) {
+                Some(current) => alien.position.0 > aliens[current.0][current.1].position.0,
+                None => true,
+            };
+            if is_lower {
+                lowest.insert(col, (row_index, index));
+            }
+        }
+    }
+
+    lowest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::entities::Dive;
+    use crate::formation::Formation;
+    use rand::SeedableRng;
+
+    fn aliens() -> Vec<Vec<Alien>> {
+        Formation::classic(640).build().unwrap()
+    }
+
+    fn director(rolling_every: u32) -> FiringDirector {
+        let mut config = Config::default().firing;
+        config.rolling_every = rolling_every;
+        FiringDirector::new(config, 0.5)
+    }
+
+    #[test]
+    fn lowest_aliens_skip_the_dead_and_diving_ones() {
+        let mut aliens = aliens();
+        aliens[4][0].hit_points = 0;
+        aliens[3][0].dive = Some(Dive { velocity: (0.0, 0.0), offset: (0.0, 0.0), returning: false });
+        for row in aliens.iter_mut() {
+            row[2].hit_points = 0;
+        }
+
+        let lowest = lowest_aliens(&aliens);
+        assert_eq!(lowest.len(), 10);
+        assert_eq!(lowest[&0], (2, 0));
+        assert_eq!(lowest[&1], (4, 1));
+        assert!(!lowest.contains_key(&2));
+    }
+
+    #[test]
+    fn no_shot_past_max_shots() {
+        let aliens = aliens();
+        let mut director = director(0);
+        let mut rng = StdRng::seed_from_u64(1);
+        let max_shots = Config::default().firing.max_shots as usize;
+
+        for _ in 0..100 {
+            assert_eq!(director.update(&aliens, 320.0, max_shots, &mut rng, 0.1), None);
+        }
+        assert!(director.update(&aliens, 320.0, max_shots - 1, &mut rng, 0.1).is_some());
+    }
+
+    #[test]
+    fn rolling_shots_come_from_the_column_nearest_the_cannon() {
+        let aliens = aliens();
+        let mut director = director(1);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for index in [0, 7, 10] {
+            let alien = &aliens[4][index].entity;
+            let cannon_x = alien.x + (alien.width / 2.0) + 1.0;
+
+            let order = director.update(&aliens, cannon_x, 0, &mut rng, 0.5).unwrap();
+            assert_eq!(order, FiringOrder { alien: (4, index), kind: Some(ShotKind::Rolling) });
+        }
+    }
+}
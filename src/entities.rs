@@ -1,56 +1,100 @@
-extern crate piston_window;
-extern crate image;
+// Identifies which image a render layer should draw for an entity
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sprite {
+    Cannon,
+    CannonShot,
+    AlienShot(ShotKind),
+    Barricade,
+    Ufo,
+    Alien(AlienSpecies, AlienVariant),
+}
+
+impl Sprite {
+    pub fn all() -> Vec<Sprite> {
+        let mut sprites = vec![Sprite::Cannon, Sprite::CannonShot, Sprite::Barricade, Sprite::Ufo];
 
-// use image::DynamicImage;
-use piston_window::*;
+        for kind in ShotKind::all() {
+            sprites.push(Sprite::AlienShot(kind));
+        }
+
+        for species in [AlienSpecies::Soldier, AlienSpecies::Bug, AlienSpecies::Squid] {
+            for variant in [AlienVariant::Default, AlienVariant::Blue, AlienVariant::Pink, AlienVariant::White] {
+                sprites.push(Sprite::Alien(species, variant));
+            }
+        }
+
+        sprites
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Entity {
     pub x: f64,
     pub y: f64,
-    // drawing_path: String,
-    // entity_image: DynamicImage,
-    pub texture: Texture<gfx_device_gl::Resources>,
+    pub width: f64,
+    pub height: f64,
+    pub sprite: Sprite,
     pub movement_speed: f64,
     pub colided: bool,
     pub scale: (f64, f64),
 }
 
 impl Entity {
-    pub fn new(window: &mut PistonWindow, x: f64, y: f64, drawing_path: String, movement_speed: f64, scale: Option<(f64, f64)>) -> Entity {
-        let img = image::open(drawing_path.clone()).unwrap();
+    // `size` is the unscaled sprite size, the hitbox is the size multiplied by the scale
+    pub fn new(x: f64, y: f64, size: (f64, f64), sprite: Sprite, movement_speed: f64, scale: Option<(f64, f64)>) -> Entity {
+        let scale = scale.unwrap_or((1.0, 1.0));
         Entity {
             x,
             y,
-            // drawing_path: drawing_path.clone(),
-            // entity_image: img.clone(),
-            texture: Texture::from_image(
-                &mut window.create_texture_context(),
-                &img.to_rgba8(),
-                &TextureSettings::new()
-            ).unwrap(),
+            width: size.0 * scale.0,
+            height: size.1 * scale.1,
+            sprite,
             movement_speed,
             colided: false,
-            scale: scale.unwrap_or((1.0, 1.0))
+            scale
         }
     }
 }
 
 pub struct Cannon {
     pub entity: Entity,
-    pub size: (f64, f64),
     pub life: i32,
+    pub exploding_time: f64,
+    pub invulnerable_time: f64,
 }
 
 impl Cannon {
-    pub fn new(window: &mut PistonWindow, x: f64, y: f64, drawing_path: String, movement_speed: f64) -> Cannon {
+    pub fn new(x: f64, y: f64, movement_speed: f64, life: i32) -> Cannon {
         Cannon {
-            entity: Entity::new(window, x, y, drawing_path, movement_speed, None),
-            size: (32.0, 32.0),
-            life: 5,
+            entity: Entity::new(x, y, (32.0, 32.0), Sprite::Cannon, movement_speed, None),
+            life,
+            exploding_time: 0.0,
+            invulnerable_time: 0.0,
         }
     }
 
+    pub fn is_exploding(&self) -> bool {
+        self.exploding_time > 0.0
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_time > 0.0
+    }
+
+    // Lose a life and start the explosion, which lasts `time` seconds
+    pub fn explode(&mut self, time: f64) {
+        self.life -= 1;
+        self.exploding_time = time;
+    }
+
+    // Back on the screen at `x`, shots can not hit it for `invulnerable_time` seconds
+    pub fn respawn(&mut self, x: f64, invulnerable_time: f64) {
+        self.entity.x = x;
+        self.entity.colided = false;
+        self.exploding_time = 0.0;
+        self.invulnerable_time = invulnerable_time;
+    }
+
     pub fn move_x_axis(&mut self, value: f64) {
         self.entity.x += value;
     }
@@ -59,49 +103,163 @@ impl Cannon {
         self.entity.movement_speed
     }
 
-    pub fn shoot(&mut self, window: &mut PistonWindow) -> Shot {
+    // `movement_speed` in pixels per second, negative goes up
+    pub fn shoot(&mut self, movement_speed: f64) -> Shot {
         Shot::new(
-            window,
-            self.entity.x + (self.size.0 * 0.40),
-            self.entity.y - ((self.size.1 * 0.20) * self.entity.scale.1),
-            String::from("src/assets/cannon-ball-18x18.png"),
-            -5.0,
+            self.entity.x + (self.entity.width * 0.40),
+            self.entity.y - (self.entity.height * 0.20),
+            Sprite::CannonShot,
+            movement_speed,
             (18.0, 18.0),
             (1.0/2.0, 1.0/2.0)
         )
     }
 }
 
+// Path followed by a shot, on top of its vertical `movement_speed`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShotMotion {
+    Straight,
+    // Sways `amplitude` pixels to each side, `frequency` times per second
+    ZigZag { amplitude: f64, frequency: f64 },
+    // Falls faster and faster, gaining `acceleration` pixels per second every second
+    Accelerating { acceleration: f64 },
+}
+
+// The arcade alien shots, each with its own sprite, speed and motion
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShotKind {
+    Straight,
+    Squiggly,
+    Plunger,
+    Rolling,
+}
+
+impl ShotKind {
+    pub fn all() -> [ShotKind; 4] {
+        [ShotKind::Straight, ShotKind::Squiggly, ShotKind::Plunger, ShotKind::Rolling]
+    }
+
+    // Multiplies the alien shot speed
+    pub fn get_speed_factor(&self) -> f64 {
+        match self {
+            ShotKind::Straight => 1.0,
+            ShotKind::Squiggly => 1.25,
+            ShotKind::Plunger => 0.7,
+            ShotKind::Rolling => 1.2,
+        }
+    }
+
+    pub fn get_motion(&self) -> ShotMotion {
+        match self {
+            ShotKind::Squiggly => ShotMotion::ZigZag { amplitude: 8.0, frequency: 3.0 },
+            ShotKind::Plunger => ShotMotion::Accelerating { acceleration: 240.0 },
+            ShotKind::Straight | ShotKind::Rolling => ShotMotion::Straight,
+        }
+    }
+
+    // Image size and scale
+    fn get_size(&self) -> ((f64, f64), (f64, f64)) {
+        match self {
+            ShotKind::Straight => ((11.0, 15.0), (3.0/5.0, 3.0/5.0)),
+            _ => ((9.0, 24.0), (1.0/2.0, 1.0/2.0)),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Shot {
     pub entity: Entity,
-    pub size: (f64, f64),
+    pub motion: ShotMotion,
+    // Sideways drift in pixels per second, added to the motion
+    pub speed_x: f64,
+    start_x: f64,
+    elapsed_time: f64,
 }
 
 impl Shot {
-    pub fn new(window: &mut PistonWindow, x: f64, y: f64, drawing_path: String, movement_speed: f64, size: (f64, f64), scale: (f64, f64)) -> Shot {
+    pub fn new(x: f64, y: f64, sprite: Sprite, movement_speed: f64, size: (f64, f64), scale: (f64, f64)) -> Shot {
         Shot {
-            entity: Entity::new(window, x, y, drawing_path, movement_speed, Some(scale)),
-            size,
+            entity: Entity::new(x, y, size, sprite, movement_speed, Some(scale)),
+            motion: ShotMotion::Straight,
+            speed_x: 0.0,
+            start_x: x,
+            elapsed_time: 0.0,
+        }
+    }
+
+    // Move along the motion path, `dt` is the elapsed time in seconds
+    pub fn advance(&mut self, dt: f64) {
+        self.elapsed_time += dt;
+        self.start_x += self.speed_x * dt;
+
+        match self.motion {
+            ShotMotion::Straight => {
+                self.entity.x = self.start_x;
+            },
+            ShotMotion::ZigZag { amplitude, frequency } => {
+                let phase = self.elapsed_time * frequency * std::f64::consts::TAU;
+                self.entity.x = self.start_x + (amplitude * phase.sin());
+            },
+            ShotMotion::Accelerating { acceleration } => {
+                self.entity.x = self.start_x;
+                self.entity.movement_speed += acceleration * dt;
+            },
+        }
+
+        self.entity.y += self.entity.movement_speed * dt;
+    }
+
+    // Seconds the shot takes to fall `distance` pixels from where it is now
+    pub fn get_fall_time(&self, distance: f64) -> f64 {
+        let speed = self.entity.movement_speed;
+
+        match self.motion {
+            ShotMotion::Accelerating { acceleration } if acceleration > 0.0 => {
+                // distance = speed * t + acceleration * t² / 2
+                (((speed * speed) + (2.0 * acceleration * distance)).sqrt() - speed) / acceleration
+            },
+            _ => distance / speed,
         }
     }
 }
 #[derive(Clone, Debug)]
 pub struct Alien {
     pub entity: Entity,
-    pub size: (f64, f64),
     variant: AlienVariant,
     species: AlienSpecies,
     pub position: (u32, u32), // row, col
     pub score: u32,
+    // Set while the alien is out of the formation
+    pub dive: Option<Dive>,
+
+    // Each hit takes its damage minus the armor, at least 1, from the hit points
+    pub hit_points: u32,
+    max_hit_points: u32,
+    armor: u32,
+    // Time left of the flash shown after a hit, in seconds
+    pub hit_flash_time: f64,
+    // Animation frame, 0 or 1, switched at each step of the step-wise march
+    frame: usize,
 }
-#[derive(Clone, Debug)]
+
+// Time an alien flashes after being hit without dying
+pub const HIT_FLASH_TIME: f64 = 0.15;
+
+// Flight of an alien out of the formation, `offset` is how far it is from its place in the formation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dive {
+    pub velocity: (f64, f64),
+    pub offset: (f64, f64),
+    pub returning: bool,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum AlienSpecies {
     Soldier,
     Bug,
     Squid
 }
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum AlienVariant {
     Default,
     Blue,
@@ -109,66 +267,240 @@ pub enum AlienVariant {
     White
 }
 
-impl Alien {
+impl AlienVariant {
+    // Next stronger variant, blue is the strongest
+    pub fn tougher(&self) -> AlienVariant {
+        match self {
+            AlienVariant::Default => AlienVariant::White,
+            AlienVariant::White => AlienVariant::Pink,
+            AlienVariant::Pink | AlienVariant::Blue => AlienVariant::Blue,
+        }
+    }
+}
 
-    pub fn new(window: &mut PistonWindow, x: f64, y: f64, movement_speed: f64, species: AlienSpecies, variant: AlienVariant, position: (u32, u32)) -> Alien {
-        let all_species = ("bug-alien-", "soldier-alien-", "squid-alien-");
-        let all_variants = ("white-", "pink-", "blue-");
+impl Alien {
 
-        let mut drawing_path = String::from("src/assets/");
-        let mut score: u32;
-        match species {
-            AlienSpecies::Bug => {
-                drawing_path = format!("{}{}", drawing_path, all_species.0);
-                score = 10;
-            },
-            AlienSpecies::Soldier => {
-                drawing_path = format!("{}{}", drawing_path, all_species.1);
-                score = 20;
-            },
-            AlienSpecies::Squid => {
-                drawing_path = format!("{}{}", drawing_path, all_species.2);
-                score = 30;
-            },
-        }
+    pub fn new(x: f64, y: f64, species: AlienSpecies, variant: AlienVariant, position: (u32, u32)) -> Alien {
+        let mut score: u32 = match species {
+            AlienSpecies::Bug => 10,
+            AlienSpecies::Soldier => 20,
+            AlienSpecies::Squid => 30,
+        };
 
         match variant {
-            AlienVariant::White => {
-                drawing_path = format!("{}{}", drawing_path, all_variants.0);
-            },
             AlienVariant::Pink => {
-                drawing_path = format!("{}{}", drawing_path, all_variants.1);
-                score = score * 2
+                score *= 2
             },
             AlienVariant::Blue => {
-                drawing_path = format!("{}{}", drawing_path, all_variants.2);
-                score = score * 3
+                score *= 3
             },
             _ => {}
         }
 
-        drawing_path = format!("{}{}", drawing_path, "32x32.png");
+        // Stronger variants take more hits, the cannon shots do 2 damage
+        let (hit_points, armor) = match variant {
+            AlienVariant::Default => (2, 0),
+            AlienVariant::White => (3, 0),
+            AlienVariant::Pink => (4, 0),
+            AlienVariant::Blue => (3, 1),
+        };
 
         Alien {
-            entity: Entity::new(window, x, y, drawing_path, movement_speed, None),
-            size: (32.0, 32.0),
+            // The world moves the whole formation, the aliens have no speed of their own
+            entity: Entity::new(x, y, (32.0, 32.0), Sprite::Alien(species, variant), 0.0, None),
             variant,
             species,
             position,
-            score
+            score,
+            dive: None,
+
+            hit_points,
+            max_hit_points: hit_points,
+            armor,
+            hit_flash_time: 0.0,
+            frame: 0,
         }
     }
 
-    pub fn shoot(&mut self, window: &mut PistonWindow) -> Shot {
-        Shot::new(
-            window,
-            self.entity.x + (self.size.0 * 0.4),
-            self.entity.y + self.size.1,
-            String::from("src/assets/alien-shot.png"),
-            3.0,
-            (11.0, 15.0),
-            (3.0/5.0, 3.0/5.0)
-        )
+    // Take a hit of `damage`, returns true when it kills the alien
+    pub fn hit(&mut self, damage: u32) -> bool {
+        let damage = damage.saturating_sub(self.armor).max(1);
+        self.hit_points = self.hit_points.saturating_sub(damage);
+        self.hit_flash_time = HIT_FLASH_TIME;
+        self.is_dead()
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.hit_points == 0
+    }
+
+    pub fn is_damaged(&self) -> bool {
+        self.hit_points < self.max_hit_points
+    }
+
+    pub fn is_flashing(&self) -> bool {
+        self.hit_flash_time > 0.0
+    }
+
+    pub fn get_max_hit_points(&self) -> u32 {
+        self.max_hit_points
+    }
+
+    pub fn get_species(&self) -> AlienSpecies {
+        self.species
+    }
+
+    pub fn get_variant(&self) -> AlienVariant {
+        self.variant
+    }
+
+    pub fn is_diving(&self) -> bool {
+        self.dive.is_some()
+    }
+
+    // Where the alien stands in the formation, even while it is diving away from it
+    pub fn get_slot_position(&self) -> (f64, f64) {
+        match self.dive {
+            Some(dive) => (self.entity.x - dive.offset.0, self.entity.y - dive.offset.1),
+            None => (self.entity.x, self.entity.y),
+        }
+    }
+
+    // Move the place of the alien in the formation, a diving alien keeps its course and only flies back further
+    pub fn move_slot(&mut self, x: f64, y: f64) {
+        match self.dive.as_mut() {
+            Some(dive) => dive.offset = (dive.offset.0 - x, dive.offset.1 - y),
+            None => {
+                self.entity.x += x;
+                self.entity.y += y;
+            },
+        }
+    }
+
+    pub fn get_frame(&self) -> usize {
+        self.frame
     }
+
+    pub fn next_frame(&mut self) {
+        self.frame = 1 - self.frame;
+    }
+
+    // `movement_speed` in pixels per second, before the speed factor of the kind
+    pub fn shoot(&mut self, kind: ShotKind, movement_speed: f64) -> Shot {
+        let (size, scale) = kind.get_size();
+
+        let mut shot = Shot::new(
+            self.entity.x + (self.entity.width * 0.4),
+            self.entity.y + self.entity.height,
+            Sprite::AlienShot(kind),
+            movement_speed * kind.get_speed_factor(),
+            size,
+            scale
+        );
+        shot.motion = kind.get_motion();
+        shot
+    }
+}
+
+// Mystery saucer crossing the top of the screen, a negative speed goes to the left
+#[derive(Clone, Debug)]
+pub struct Ufo {
+    pub entity: Entity,
 }
 
+impl Ufo {
+    pub fn new(x: f64, y: f64, movement_speed: f64) -> Ufo {
+        Ufo {
+            entity: Entity::new(x, y, (32.0, 14.0), Sprite::Ufo, movement_speed, None),
+        }
+    }
+
+    pub fn move_x_axis(&mut self, dt: f64) {
+        self.entity.x += self.entity.movement_speed * dt;
+    }
+}
+
+// Number of cells in each barricade row and column, each one is a 4x4 block of the sprite
+pub const BARRICADE_CELLS: usize = 16;
+
+#[derive(Clone, Debug)]
+pub struct Barricade {
+    pub entity: Entity,
+    pub cells: Vec<Vec<bool>>, // row, col -> cell still standing
+}
+
+impl Barricade {
+    pub fn new(x: f64, y: f64) -> Barricade {
+        // Arch shape of barricade-64x64.png: rounded top, a solid bar and two legs
+        let cells = (0..BARRICADE_CELLS).map(|row| {
+            (0..BARRICADE_CELLS).map(|col| match row {
+                0 | 1 => (2..BARRICADE_CELLS - 2).contains(&col),
+                2 => true,
+                _ => !(3..BARRICADE_CELLS - 3).contains(&col),
+            }).collect()
+        }).collect();
+
+        Barricade {
+            entity: Entity::new(x, y, (64.0, 64.0), Sprite::Barricade, 0.0, None),
+            cells,
+        }
+    }
+
+    pub fn get_cell_size(&self) -> (f64, f64) {
+        (self.entity.width / BARRICADE_CELLS as f64, self.entity.height / BARRICADE_CELLS as f64)
+    }
+
+    // Standing cells overlapped by the entity hitbox
+    fn overlapped_cells(&self, entity: &Entity) -> Vec<(usize, usize)> {
+        let cell_size = self.get_cell_size();
+        let mut overlapped = vec![];
+
+        for row in 0..BARRICADE_CELLS {
+            for col in 0..BARRICADE_CELLS {
+                if !self.cells[row][col] {
+                    continue;
+                }
+
+                let cell_x = self.entity.x + (col as f64 * cell_size.0);
+                let cell_y = self.entity.y + (row as f64 * cell_size.1);
+
+                if entity.x < cell_x + cell_size.0 && entity.x + entity.width > cell_x
+                    && entity.y < cell_y + cell_size.1 && entity.y + entity.height > cell_y {
+                    overlapped.push((row, col));
+                }
+            }
+        }
+
+        overlapped
+    }
+
+    // Erode the cells hit by a shot and their neighbours, returns if anything was hit
+    pub fn hit(&mut self, entity: &Entity) -> bool {
+        let overlapped = self.overlapped_cells(entity);
+
+        for (row, col) in overlapped.iter() {
+            for (neighbour_row, neighbour_col) in [(0, 0), (-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let neighbour_row = *row as i32 + neighbour_row;
+                let neighbour_col = *col as i32 + neighbour_col;
+
+                if neighbour_row >= 0 && neighbour_row < BARRICADE_CELLS as i32
+                    && neighbour_col >= 0 && neighbour_col < BARRICADE_CELLS as i32 {
+                    self.cells[neighbour_row as usize][neighbour_col as usize] = false;
+                }
+            }
+        }
+
+        !overlapped.is_empty()
+    }
+
+    // Erase every cell under the entity, used by aliens marching through the barricade
+    pub fn erase(&mut self, entity: &Entity) {
+        for (row, col) in self.overlapped_cells(entity) {
+            self.cells[row][col] = false;
+        }
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.cells.iter().all(|row| row.iter().all(|cell| !cell))
+    }
+}
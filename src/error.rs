@@ -0,0 +1,31 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum GameError {
+    AssetLoad(String),
+    WindowCreation(String),
+    InvalidFormation(String),
+    ConfigParse(String),
+    ReplayFile(String),
+    Terminal(String),
+    InvalidArgument(String),
+    LevelParse(String),
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::AssetLoad(message) => write!(f, "Asset load error: {}", message),
+            GameError::WindowCreation(message) => write!(f, "Window creation error: {}", message),
+            GameError::InvalidFormation(message) => write!(f, "Invalid formation: {}", message),
+            GameError::ConfigParse(message) => write!(f, "Config parse error: {}", message),
+            GameError::ReplayFile(message) => write!(f, "Replay file error: {}", message),
+            GameError::Terminal(message) => write!(f, "Terminal error: {}", message),
+            GameError::InvalidArgument(message) => write!(f, "Invalid argument: {}", message),
+            GameError::LevelParse(message) => write!(f, "Level parse error: {}", message),
+        }
+    }
+}
+
+impl Error for GameError {}
@@ -0,0 +1,179 @@
+use crate::entities::{Alien, AlienSpecies, AlienVariant};
+use crate::error::GameError;
+use std::collections::HashSet;
+
+const ALIEN_SIZE: f64 = 32.0;
+// Top of the first row and distance between rows
+const ORIGIN_Y: f64 = 64.0;
+const ROW_SPACING: f64 = ALIEN_SIZE * 1.25;
+// Free width, in aliens, the widest formation still leaves to march across the window
+const MARCH_ROOM: f64 = 3.0;
+// Size of the classic formation, the campaign levels are no larger
+pub const CLASSIC_ROWS: usize = 5;
+pub const CLASSIC_COLUMNS: usize = 11;
+
+// Lays out a rows x cols grid of aliens, a cell without a kind is left empty
+#[derive(Clone, Debug)]
+pub struct Formation {
+    rows: u32,
+    cols: u32,
+    origin: (f64, f64),
+    spacing: (f64, f64),
+    kinds: Vec<Vec<Option<(AlienSpecies, AlienVariant)>>>,
+}
+
+impl Formation {
+    pub fn new(rows: u32, cols: u32) -> Formation {
+        Formation {
+            rows,
+            cols,
+            origin: (64.0, 64.0),
+            spacing: (ALIEN_SIZE * 1.5, ALIEN_SIZE * 1.5),
+            kinds: vec![vec![Some((AlienSpecies::Soldier, AlienVariant::Default)); cols as usize]; rows as usize],
+        }
+    }
+
+    // Formation with the given kind in each cell, centered like the classic one
+    pub fn from_grid(width: u32, grid: Vec<Vec<Option<(AlienSpecies, AlienVariant)>>>) -> Formation {
+        let rows = grid.len() as u32;
+        let cols = grid.iter().map(|row| row.len()).max().unwrap_or(0) as u32;
+
+        let mut formation = Formation::centered(width, rows, cols);
+        formation.kinds = grid.into_iter()
+            .map(|mut kinds| {
+                kinds.resize(cols as usize, None);
+                kinds
+            })
+            .collect();
+        formation
+    }
+
+    // Classic 5x11 formation: squids on the top row, soldiers in the middle and bugs at the bottom
+    pub fn classic(width: u32) -> Formation {
+        Formation::centered(width, CLASSIC_ROWS as u32, CLASSIC_COLUMNS as u32)
+            .row(0, AlienSpecies::Squid, AlienVariant::Default)
+            .row(1, AlienSpecies::Soldier, AlienVariant::Default)
+            .row(2, AlienSpecies::Soldier, AlienVariant::Default)
+            .row(3, AlienSpecies::Bug, AlienVariant::Default)
+            .row(4, AlienSpecies::Bug, AlienVariant::Default)
+    }
+
+    fn centered(width: u32, rows: u32, cols: u32) -> Formation {
+        // The formation takes 2/3 of the window width, leaving room to march
+        let spacing_x = (width as f64 * 2.0 / 3.0 / cols.max(1) as f64).max(ALIEN_SIZE);
+        let formation_width = spacing_x * cols.saturating_sub(1) as f64 + ALIEN_SIZE;
+        let origin_x = ((width as f64 - formation_width) / 2.0).max(0.0);
+
+        Formation::new(rows, cols)
+            .origin(origin_x, ORIGIN_Y)
+            .spacing(spacing_x, ROW_SPACING)
+    }
+
+    pub fn origin(mut self, x: f64, y: f64) -> Formation {
+        self.origin = (x, y);
+        self
+    }
+
+    pub fn spacing(mut self, x: f64, y: f64) -> Formation {
+        self.spacing = (x, y);
+        self
+    }
+
+    pub fn row(mut self, row: u32, species: AlienSpecies, variant: AlienVariant) -> Formation {
+        if let Some(kinds) = self.kinds.get_mut(row as usize) {
+            for kind in kinds.iter_mut() {
+                *kind = Some((species, variant));
+            }
+        }
+        self
+    }
+
+    // Use the same variant for every row, keeping each row species
+    pub fn variant(mut self, variant: AlienVariant) -> Formation {
+        for kind in self.kinds.iter_mut().flat_map(|row| row.iter_mut()).flatten() {
+            kind.1 = variant;
+        }
+        self
+    }
+
+    // Make every alien `steps` variants stronger, keeping each cell species
+    pub fn tougher(mut self, steps: u32) -> Formation {
+        for kind in self.kinds.iter_mut().flat_map(|row| row.iter_mut()).flatten() {
+            for _ in 0..steps {
+                kind.1 = kind.1.tougher();
+            }
+        }
+        self
+    }
+
+    pub fn get_origin(&self) -> (f64, f64) {
+        self.origin
+    }
+
+    pub fn build(&self) -> Result<Vec<Vec<Alien>>, GameError> {
+        if self.rows == 0 || self.cols == 0 {
+            return Err(GameError::InvalidFormation(format!("empty {}x{} grid", self.rows, self.cols)));
+        }
+        if self.spacing.0 < ALIEN_SIZE || self.spacing.1 < ALIEN_SIZE {
+            return Err(GameError::InvalidFormation(format!("spacing {:?} smaller than the aliens", self.spacing)));
+        }
+
+        let mut aliens = vec![];
+        for row in 0..self.rows {
+            let mut aliens_row = vec![];
+            for col in 0..self.cols {
+                let (species, variant) = match self.kinds[row as usize][col as usize] {
+                    Some(kind) => kind,
+                    None => continue,
+                };
+
+                aliens_row.push(Alien::new(
+                    self.origin.0 + (col as f64 * self.spacing.0),
+                    self.origin.1 + (row as f64 * self.spacing.1),
+                    species,
+                    variant,
+                    (row, col)
+                ));
+            }
+            aliens.push(aliens_row);
+        }
+
+        if aliens.iter().all(|row| row.is_empty()) {
+            return Err(GameError::InvalidFormation(String::from("no aliens")));
+        }
+
+        validate_formation(&aliens)?;
+        Ok(aliens)
+    }
+}
+
+// Largest number of columns that fits a window `width` pixels wide with room to march
+pub fn max_columns(width: u32) -> usize {
+    ((width as f64 / ALIEN_SIZE) - MARCH_ROOM).max(0.0) as usize
+}
+
+// Largest number of rows that stays above `bottom`, the top of the barricades, in pixels
+pub fn max_rows(bottom: f64) -> usize {
+    if bottom < ORIGIN_Y + ALIEN_SIZE {
+        return 0;
+    }
+    ((bottom - ORIGIN_Y - ALIEN_SIZE) / ROW_SPACING) as usize + 1
+}
+
+// Narrowest window `columns` aliens fit in with room to march
+pub fn min_width(columns: usize) -> u32 {
+    ((columns as f64 + MARCH_ROOM) * ALIEN_SIZE).ceil() as u32
+}
+
+// Every alien must have its own (row, col) position
+pub fn validate_formation(aliens: &[Vec<Alien>]) -> Result<(), GameError> {
+    let mut positions: HashSet<(u32, u32)> = HashSet::new();
+
+    for alien in aliens.iter().flat_map(|row| row.iter()) {
+        if !positions.insert(alien.position) {
+            return Err(GameError::InvalidFormation(format!("more than one alien at position {:?}", alien.position)));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use crate::error::GameError;
+use crate::state::Session;
+use crate::world::{InputState, FIXED_TIMESTEP};
+
+// Runs a match without a window or terminal, as fast as possible, for tests and automation
+pub struct Game {
+    session: Session,
+}
+
+impl Game {
+    pub fn new(session: Session) -> Game {
+        Game {
+            session,
+        }
+    }
+
+    pub fn start_game(&mut self) -> Result<(), GameError> {
+        // Without a player the match starts right away, a replay being played back already did
+        if !self.session.is_world_running() {
+            self.session.reset()?;
+        }
+
+        while self.session.is_world_running() {
+            self.session.advance(InputState::default(), FIXED_TIMESTEP)?;
+        }
+
+        let world = &self.session.world;
+        println!("Score: {} Wave: {} Steps: {}", world.game_score, world.wave, self.session.recording.inputs.len());
+        Ok(())
+    }
+
+    pub fn get_session(&self) -> &Session {
+        &self.session
+    }
+}
@@ -0,0 +1,44 @@
+use crate::world::World;
+
+// Heads-up display data, shared by every front-end
+#[derive(Clone, Debug)]
+pub struct Hud {
+    pub score: u32,
+    pub high_score: u32,
+    pub lives: u32,
+    pub wave: u32,
+    pub level_name: Option<String>,
+}
+
+impl Hud {
+    pub fn new(world: &World) -> Hud {
+        Hud {
+            score: world.game_score,
+            high_score: world.high_score,
+            lives: world.cannon.life.max(0) as u32,
+            wave: world.wave,
+            level_name: world.level_name.clone(),
+        }
+    }
+
+    pub fn score_text(&self) -> String {
+        format!("SCORE {:05}", self.score)
+    }
+
+    pub fn high_score_text(&self) -> String {
+        format!("HI {:05}", self.high_score)
+    }
+
+    pub fn wave_text(&self) -> String {
+        format!("WAVE {}", self.wave)
+    }
+
+    // Shown under the wave, empty without levels
+    pub fn level_text(&self) -> String {
+        self.level_name.as_deref().unwrap_or("").to_uppercase()
+    }
+
+    pub fn lives_text(&self) -> String {
+        self.lives.to_string()
+    }
+}
@@ -0,0 +1,380 @@
+use crate::entities::{AlienSpecies, AlienVariant, ShotKind};
+use crate::error::GameError;
+use crate::formation::{max_columns, max_rows, Formation};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+// Levels played in order by default, the campaign starts over once they are all cleared
+const CAMPAIGN: [(&str, &str); 5] = [
+    ("01-classic.level", include_str!("levels/01-classic.level")),
+    ("02-white-wall.level", include_str!("levels/02-white-wall.level")),
+    ("03-checkerboard.level", include_str!("levels/03-checkerboard.level")),
+    ("04-arrow.level", include_str!("levels/04-arrow.level")),
+    ("05-fortress.level", include_str!("levels/05-fortress.level")),
+];
+
+const EMPTY_CELL: char = '.';
+const BARRICADE_CELL: char = '=';
+
+// A single wave, the settings it does not give come from the config
+#[derive(Clone, Debug)]
+pub struct Level {
+    pub name: String,
+    pub formation: Formation,
+    pub movement_speed: Option<f64>,
+    pub shot_interval: Option<f64>,
+    // Fraction of an alien height the formation goes down at each side of the window
+    pub drop: Option<f64>,
+    // Kind of every alien shot, each species fires its own when missing
+    pub shot_kind: Option<ShotKind>,
+    // Center of each barricade, as a fraction of the window width
+    pub barricades: Option<Vec<f64>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Section {
+    Level,
+    Legend,
+    Formation,
+    Barricades,
+}
+
+// Parses level files into formations laid out for a window `width` pixels wide, above barricades at `barricades_y`
+//
+// A level file has up to four sections, lines starting with '#' are comments:
+//
+//   [level]        name = ..., speed = ..., fire_interval = ..., drop = ..., shot = ...
+//   [legend]       X = species [variant], Q, S and B are squids, soldiers and bugs by default
+//   [formation]    one line per row of aliens, '.' is an empty cell
+//   [barricades]   a single line spanning the window, each run of '=' is a barricade
+pub struct LevelLoader {
+    width: u32,
+    barricades_y: f64,
+}
+
+impl LevelLoader {
+    pub fn new(width: u32, barricades_y: f64) -> LevelLoader {
+        LevelLoader {
+            width,
+            barricades_y,
+        }
+    }
+
+    pub fn load(&self, path: &Path) -> Result<Level, GameError> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| GameError::LevelParse(format!("{}: {}", path.display(), error)))?;
+        self.parse(&path.display().to_string(), &text)
+    }
+
+    pub fn campaign(&self) -> Result<Vec<Level>, GameError> {
+        CAMPAIGN.iter()
+            .map(|(file_name, text)| self.parse(file_name, text))
+            .collect()
+    }
+
+    // `file_name` is only used in the error messages
+    pub fn parse(&self, file_name: &str, text: &str) -> Result<Level, GameError> {
+        let error = |line: usize, col: usize, message: String| {
+            GameError::LevelParse(format!("{}:{}:{}: {}", file_name, line, col, message))
+        };
+
+        let mut name = file_name.to_string();
+        let mut movement_speed = None;
+        let mut shot_interval = None;
+        let mut drop = None;
+        let mut shot_kind = None;
+        let mut legend: HashMap<char, (AlienSpecies, AlienVariant)> = HashMap::from([
+            ('Q', (AlienSpecies::Squid, AlienVariant::Default)),
+            ('S', (AlienSpecies::Soldier, AlienVariant::Default)),
+            ('B', (AlienSpecies::Bug, AlienVariant::Default)),
+        ]);
+        let mut grid: Vec<Vec<Option<(AlienSpecies, AlienVariant)>>> = vec![];
+        let mut barricades = None;
+
+        let mut section = None;
+        let mut formation_line = None;
+
+        for (index, raw_line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = raw_line.trim_end();
+            let indent = line.len() - line.trim_start().len();
+
+            if line.trim_start().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            if line.trim_start().starts_with('[') {
+                section = Some(match line.trim() {
+                    "[level]" => Section::Level,
+                    "[legend]" => Section::Legend,
+                    "[formation]" => Section::Formation,
+                    "[barricades]" => Section::Barricades,
+                    other => return Err(error(line_number, indent + 1, format!("unknown section {}", other))),
+                });
+                if section == Some(Section::Formation) {
+                    formation_line = Some(line_number);
+                }
+                continue;
+            }
+
+            match section {
+                None => return Err(error(line_number, indent + 1, String::from("expected a section, like [level]"))),
+                Some(Section::Level) => {
+                    let (key, value, value_col) = split_key_value(line)
+                        .ok_or_else(|| error(line_number, indent + 1, String::from("expected key = value")))?;
+
+                    let number = || value.parse::<f64>()
+                        .ok()
+                        .filter(|number| number.is_finite() && *number >= 0.0)
+                        .ok_or_else(|| error(line_number, value_col, format!("invalid {} {}, expected a number", key, value)));
+                    match key {
+                        "name" => name = value.to_string(),
+                        "speed" => {
+                            let speed = number()?;
+                            if speed <= 0.0 {
+                                return Err(error(line_number, value_col, String::from("speed must be greater than 0")));
+                            }
+                            movement_speed = Some(speed);
+                        },
+                        "fire_interval" => {
+                            let interval = number()?;
+                            if interval <= 0.0 {
+                                return Err(error(line_number, value_col, String::from("fire_interval must be greater than 0")));
+                            }
+                            shot_interval = Some(interval);
+                        },
+                        "drop" => drop = Some(number()?),
+                        "shot" => shot_kind = Some(match value {
+                            "straight" => ShotKind::Straight,
+                            "squiggly" => ShotKind::Squiggly,
+                            "plunger" => ShotKind::Plunger,
+                            "rolling" => ShotKind::Rolling,
+                            _ => return Err(error(line_number, value_col, format!("unknown shot {}, expected straight, squiggly, plunger or rolling", value))),
+                        }),
+                        _ => return Err(error(line_number, indent + 1, format!("unknown key {}", key))),
+                    }
+                },
+                Some(Section::Legend) => {
+                    let (key, value, value_col) = split_key_value(line)
+                        .ok_or_else(|| error(line_number, indent + 1, String::from("expected symbol = species [variant]")))?;
+
+                    let mut symbols = key.chars();
+                    let symbol = match (symbols.next(), symbols.next()) {
+                        (Some(symbol), None) if symbol != EMPTY_CELL => symbol,
+                        _ => return Err(error(line_number, indent + 1, format!("invalid symbol {}, expected a single character other than '{}'", key, EMPTY_CELL))),
+                    };
+
+                    let mut words = value.split_whitespace();
+                    let species = match words.next() {
+                        Some("squid") => AlienSpecies::Squid,
+                        Some("soldier") => AlienSpecies::Soldier,
+                        Some("bug") => AlienSpecies::Bug,
+                        other => return Err(error(line_number, value_col, format!("unknown species {}, expected squid, soldier or bug", other.unwrap_or("")))),
+                    };
+                    let variant = match words.next() {
+                        None | Some("default") => AlienVariant::Default,
+                        Some("white") => AlienVariant::White,
+                        Some("pink") => AlienVariant::Pink,
+                        Some("blue") => AlienVariant::Blue,
+                        Some(other) => {
+                            let col = value_col + value.find(other).unwrap_or(0);
+                            return Err(error(line_number, col, format!("unknown variant {}, expected default, white, pink or blue", other)));
+                        },
+                    };
+                    if let Some(extra) = words.next() {
+                        let col = value_col + value.rfind(extra).unwrap_or(0);
+                        return Err(error(line_number, col, format!("unexpected {}", extra)));
+                    }
+
+                    legend.insert(symbol, (species, variant));
+                },
+                Some(Section::Formation) => {
+                    if line.chars().count() > max_columns(self.width) {
+                        return Err(error(line_number, max_columns(self.width) + 1, format!(
+                            "the formation is too wide, at most {} columns fit a window {} pixels wide", max_columns(self.width), self.width
+                        )));
+                    }
+
+                    if grid.len() >= max_rows(self.barricades_y) {
+                        return Err(error(line_number, indent + 1, format!(
+                            "the formation is too tall, at most {} rows fit above the barricades", max_rows(self.barricades_y)
+                        )));
+                    }
+
+                    let mut row = vec![];
+                    for (col, symbol) in line.chars().enumerate() {
+                        match symbol {
+                            EMPTY_CELL | ' ' => row.push(None),
+                            _ => match legend.get(&symbol) {
+                                Some(kind) => row.push(Some(*kind)),
+                                None => return Err(error(line_number, col + 1, format!("unknown alien '{}', add it to the [legend]", symbol))),
+                            },
+                        }
+                    }
+                    grid.push(row);
+                },
+                Some(Section::Barricades) => {
+                    if barricades.is_some() {
+                        return Err(error(line_number, indent + 1, String::from("the barricades take a single line")));
+                    }
+
+                    let cells: Vec<char> = line.chars().collect();
+                    let mut centers = vec![];
+                    let mut run_start = None;
+                    for (col, symbol) in cells.iter().chain([EMPTY_CELL].iter()).enumerate() {
+                        match (*symbol, run_start) {
+                            (BARRICADE_CELL, None) => run_start = Some(col),
+                            (BARRICADE_CELL, Some(_)) => {},
+                            (EMPTY_CELL, Some(start)) | (' ', Some(start)) => {
+                                centers.push((start + col) as f64 / 2.0 / cells.len() as f64);
+                                run_start = None;
+                            },
+                            (EMPTY_CELL, None) | (' ', None) => {},
+                            (other, _) => return Err(error(line_number, col + 1, format!("unexpected '{}', expected '{}' or '{}'", other, BARRICADE_CELL, EMPTY_CELL))),
+                        }
+                    }
+                    barricades = Some(centers);
+                },
+            }
+        }
+
+        let formation_line = formation_line
+            .ok_or_else(|| GameError::LevelParse(format!("{}: missing [formation] section", file_name)))?;
+        if grid.iter().all(|row| row.iter().all(|kind| kind.is_none())) {
+            return Err(error(formation_line, 1, String::from("the formation has no aliens")));
+        }
+
+        Ok(Level {
+            name,
+            formation: Formation::from_grid(self.width, grid),
+            movement_speed,
+            shot_interval,
+            drop,
+            shot_kind,
+            barricades,
+        })
+    }
+}
+
+// Split "key = value" into the trimmed key and value, with the 1-based column of the value
+fn split_key_value(line: &str) -> Option<(&str, &str, usize)> {
+    let (key, value) = line.split_once('=')?;
+    let key = key.trim();
+    let value_col = line.len() - value.trim_start().len() + 1;
+    let value = value.trim();
+
+    if key.is_empty() || value.is_empty() {
+        return None;
+    }
+    Some((key, value, value_col))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Level, GameError> {
+        LevelLoader::new(640, 284.0).parse("test.level", text)
+    }
+
+    #[test]
+    fn campaign_levels_parse() {
+        let levels = LevelLoader::new(640, 284.0).campaign().unwrap();
+        assert_eq!(levels.len(), CAMPAIGN.len());
+        for level in levels.iter() {
+            assert!(level.formation.build().is_ok(), "{} does not build", level.name);
+        }
+    }
+
+    #[test]
+    fn full_level() {
+        let level = parse("\
+# A comment before the first section
+[level]
+name = Two Rows
+speed = 150
+fire_interval = 0.5
+drop = 0.25
+shot = rolling
+
+[legend]
+X = soldier pink
+
+[formation]
+Q.X
+  B
+
+[barricades]
+==....==
+").unwrap();
+
+        assert_eq!(level.name, "Two Rows");
+        assert_eq!(level.movement_speed, Some(150.0));
+        assert_eq!(level.shot_interval, Some(0.5));
+        assert_eq!(level.drop, Some(0.25));
+        assert_eq!(level.shot_kind, Some(ShotKind::Rolling));
+        assert_eq!(level.barricades, Some(vec![0.125, 0.875]));
+
+        let aliens = level.formation.build().unwrap();
+        let kinds: Vec<Vec<_>> = aliens.iter()
+            .map(|row| row.iter().map(|alien| (alien.position, alien.get_species(), alien.get_variant())).collect())
+            .collect();
+        assert_eq!(kinds, vec![
+            vec![((0, 0), AlienSpecies::Squid, AlienVariant::Default), ((0, 2), AlienSpecies::Soldier, AlienVariant::Pink)],
+            vec![((1, 2), AlienSpecies::Bug, AlienVariant::Default)],
+        ]);
+    }
+
+    #[test]
+    fn missing_settings_come_from_the_config() {
+        let level = parse("[formation]\nQSB\n").unwrap();
+
+        assert_eq!(level.name, "test.level");
+        assert_eq!(level.movement_speed, None);
+        assert_eq!(level.shot_interval, None);
+        assert_eq!(level.drop, None);
+        assert_eq!(level.shot_kind, None);
+        assert_eq!(level.barricades, None);
+    }
+
+    #[test]
+    fn errors_point_at_the_line_and_column() {
+        let cases = [
+            ("Q\n", "test.level:1:1: expected a section"),
+            ("[level]\n[waves]\n", "test.level:2:1: unknown section [waves]"),
+            ("[level]\nname Arrow\n", "test.level:2:1: expected key = value"),
+            ("[level]\nlives = 3\n", "test.level:2:1: unknown key lives"),
+            ("[level]\nspeed = fast\n", "test.level:2:9: invalid speed fast"),
+            ("[level]\nspeed = 0\n", "test.level:2:9: speed must be greater than 0"),
+            ("[level]\nfire_interval = 0\n", "test.level:2:17: fire_interval must be greater than 0"),
+            ("[level]\ndrop = -1\n", "test.level:2:8: invalid drop -1"),
+            ("[level]\nshot = laser\n", "test.level:2:8: unknown shot laser"),
+            ("[legend]\nXY = squid\n", "test.level:2:1: invalid symbol XY"),
+            ("[legend]\n. = squid\n", "test.level:2:1: invalid symbol ."),
+            ("[legend]\nX = dragon\n", "test.level:2:5: unknown species dragon"),
+            ("[legend]\nX = squid gold\n", "test.level:2:11: unknown variant gold"),
+            ("[legend]\nX = squid pink big\n", "test.level:2:16: unexpected big"),
+            ("[formation]\nQ.S\nQ.Z\n", "test.level:3:3: unknown alien 'Z'"),
+            ("[formation]\nQQQQQQQQQQQQQQQQQQ\n", "test.level:2:18: the formation is too wide, at most 17 columns"),
+            ("[formation]\nQ\nQ\nQ\nQ\nQ\nQ\n", "test.level:7:1: the formation is too tall, at most 5 rows fit above the barricades"),
+            ("[formation]\n...\n", "test.level:1:1: the formation has no aliens"),
+            ("[formation]\nQ\n[barricades]\n==\n==\n", "test.level:5:1: the barricades take a single line"),
+            ("[formation]\nQ\n[barricades]\n==#=\n", "test.level:4:3: unexpected '#'"),
+            ("[level]\nname = Empty\n", "test.level: missing [formation] section"),
+        ];
+
+        for (text, expected) in cases {
+            let message = parse(text).unwrap_err().to_string();
+            assert!(message.contains(expected), "expected {:?}, got {:?}", expected, message);
+        }
+    }
+
+    #[test]
+    fn widest_formation_fits() {
+        let level = LevelLoader::new(320, 284.0).parse("test.level", "[formation]\nQQQQQQQ\n").unwrap();
+        let aliens = level.formation.build().unwrap();
+        let last = aliens[0].last().unwrap();
+        assert!(aliens[0][0].entity.x > 0.0 && last.entity.x + last.entity.width < 320.0);
+    }
+}
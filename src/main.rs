@@ -1,12 +1,106 @@
 mod game;
+mod console_game;
+mod headless_game;
+mod cli;
+mod config;
 mod entities;
+mod behavior;
+mod director;
+mod world;
+mod render;
+mod asset_manager;
+mod error;
+mod formation;
+mod level;
+mod font;
+mod hud;
+mod state;
+mod replay;
+mod sound;
 extern crate piston_window;
 
+use cli::{Frontend, USAGE};
+use config::Config;
+use error::GameError;
+use level::LevelLoader;
 use game::{Game};
+use replay::Replay;
+use state::Session;
+use world::World;
+use std::env;
+use std::process;
 
 fn main() {
 
-    let mut game = Game::default();
+    if let Err(error) = run() {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
 
-    game.start_game();
-}
\ No newline at end of file
+fn run() -> Result<(), GameError> {
+    let options = cli::parse_args(env::args().skip(1))?;
+    if options.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let config = match &options.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+
+    let replay = match &options.replay {
+        Some(path) => Some(Replay::load(path)?),
+        None => None,
+    };
+
+    // A replay brings the seed of its match, a random one is used when none is given
+    let seed = match (&replay, options.seed) {
+        (Some(replay), _) => replay.seed,
+        (None, Some(seed)) => seed,
+        (None, None) => rand::random(),
+    };
+    println!("Seed: {}", seed);
+
+    let loader = LevelLoader::new(config.window.width, config.get_barricades_y());
+    let levels = if options.levels.is_empty() {
+        loader.campaign()?
+    } else {
+        options.levels.iter()
+            .map(|path| loader.load(path))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut world = World::new(config, seed);
+    world.levels = levels;
+
+    let mut session = Session::new(world);
+    if let Some(replay) = replay {
+        session.start_playback(replay)?;
+    }
+
+    let recording = match options.frontend {
+        Frontend::Window => {
+            let mut game = Game::new(session, options.scale)?;
+            game.start_game()?;
+            game.get_session().recording.clone()
+        },
+        Frontend::Terminal => {
+            let mut game = console_game::Game::new(session);
+            game.start_game()?;
+            game.get_session().recording.clone()
+        },
+        Frontend::Headless => {
+            let mut game = headless_game::Game::new(session);
+            game.start_game()?;
+            game.get_session().recording.clone()
+        },
+    };
+
+    if let Some(path) = &options.record {
+        recording.save(path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,225 @@
+extern crate piston_window;
+
+use piston_window::*;
+use crate::asset_manager::AssetManager;
+use crate::entities::{Alien, Barricade, Cannon, Entity, Sprite, Ufo};
+use crate::font::{glyph, text_width, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::hud::Hud;
+use crate::state::{GameState, Session};
+use crate::world::ScorePopup;
+
+pub const BACKGROUND_COLOR: [f32; 4] = [1.0; 4];
+pub const TEXT_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+const UFO_COLOR: [f32; 4] = [0.85, 0.1, 0.1, 1.0];
+const EXPLOSION_COLORS: [[f32; 4]; 2] = [[1.0, 0.5, 0.0, 1.0], [1.0, 0.9, 0.2, 1.0]];
+const UFO_DOME_COLOR: [f32; 4] = [0.95, 0.6, 0.6, 1.0];
+const HIT_FLASH_COLOR: [f32; 4] = [1.0, 0.2, 0.2, 0.6];
+// Opacity of an alien with a single hit point left, full health aliens are opaque
+const DAMAGED_ALIEN_OPACITY: f32 = 0.4;
+// Second animation frame of the aliens: the bottom of the sprite, its legs, is split and spread apart
+const ALIEN_LEGS_HEIGHT: f64 = 0.3;
+const ALIEN_LEGS_SPREAD: f64 = 2.0;
+
+pub fn draw_entity(assets: &AssetManager, entity: &Entity, context: Context, graphics: &mut G2d) {
+    if let Some(texture) = assets.get_sprite_texture(entity.sprite) {
+        let transform = context.transform.trans(entity.x, entity.y).scale(entity.scale.0, entity.scale.1);
+        image(texture, transform, graphics);
+    }
+}
+
+// Damaged aliens fade as they lose hit points, and flash right after a hit
+pub fn draw_alien(assets: &AssetManager, alien: &Alien, context: Context, graphics: &mut G2d) {
+    let entity = &alien.entity;
+
+    if let Some(texture) = assets.get_sprite_texture(entity.sprite) {
+        let health = alien.hit_points as f32 / alien.get_max_hit_points().max(1) as f32;
+        let opacity = if alien.is_damaged() { DAMAGED_ALIEN_OPACITY + ((1.0 - DAMAGED_ALIEN_OPACITY) * health) } else { 1.0 };
+
+        let transform = context.transform.trans(entity.x, entity.y).scale(entity.scale.0, entity.scale.1);
+        let image = Image::new_color([1.0, 1.0, 1.0, opacity]);
+
+        if alien.get_frame() == 0 {
+            image.draw(texture, &context.draw_state, transform, graphics);
+        } else {
+            let (width, height) = (texture.get_width() as f64, texture.get_height() as f64);
+            let legs_y = height * (1.0 - ALIEN_LEGS_HEIGHT);
+            let parts = [
+                [0.0, 0.0, width, legs_y, 0.0],
+                [0.0, legs_y, width / 2.0, height - legs_y, -ALIEN_LEGS_SPREAD],
+                [width / 2.0, legs_y, width / 2.0, height - legs_y, ALIEN_LEGS_SPREAD],
+            ];
+
+            for [x, y, part_width, part_height, offset] in parts {
+                image.src_rect([x, y, part_width, part_height])
+                    .rect([x + offset, y, part_width, part_height])
+                    .draw(texture, &context.draw_state, transform, graphics);
+            }
+        }
+    }
+
+    if alien.is_flashing() {
+        rectangle(HIT_FLASH_COLOR, [entity.x, entity.y, entity.width, entity.height], context.transform, graphics);
+    }
+}
+
+// Draw the barricade sprite, covering the destroyed cells with the background
+pub fn draw_barricade(assets: &AssetManager, barricade: &Barricade, context: Context, graphics: &mut G2d) {
+    draw_entity(assets, &barricade.entity, context, graphics);
+
+    let cell_size = barricade.get_cell_size();
+    for (row, cells) in barricade.cells.iter().enumerate() {
+        for (col, standing) in cells.iter().enumerate() {
+            if !standing {
+                rectangle(
+                    BACKGROUND_COLOR,
+                    [barricade.entity.x + (col as f64 * cell_size.0), barricade.entity.y + (row as f64 * cell_size.1), cell_size.0, cell_size.1],
+                    context.transform,
+                    graphics
+                );
+            }
+        }
+    }
+}
+
+// Exploding cannon as flickering debris, blinking while invulnerable
+pub fn draw_cannon(assets: &AssetManager, cannon: &Cannon, context: Context, graphics: &mut G2d) {
+    let entity = &cannon.entity;
+
+    if cannon.is_exploding() {
+        let frame = (cannon.exploding_time * 10.0) as usize;
+        for index in 0..6 {
+            let color = EXPLOSION_COLORS[(frame + index) % EXPLOSION_COLORS.len()];
+            let offset_x = ((frame * 7 + index * 13) % 24) as f64;
+            let offset_y = ((frame * 5 + index * 11) % 16) as f64;
+            rectangle(
+                color,
+                [entity.x + offset_x, entity.y + 12.0 + offset_y, 6.0, 4.0],
+                context.transform,
+                graphics
+            );
+        }
+        return;
+    }
+
+    if cannon.is_invulnerable() && (cannon.invulnerable_time * 10.0) as i32 % 2 == 0 {
+        return;
+    }
+
+    draw_entity(assets, entity, context, graphics);
+}
+
+// The UFO has no image, it is drawn as a saucer with a dome
+pub fn draw_ufo(ufo: &Ufo, context: Context, graphics: &mut G2d) {
+    let entity = &ufo.entity;
+
+    ellipse(
+        UFO_DOME_COLOR,
+        [entity.x + (entity.width * 0.3), entity.y, entity.width * 0.4, entity.height * 0.7],
+        context.transform,
+        graphics
+    );
+    ellipse(
+        UFO_COLOR,
+        [entity.x, entity.y + (entity.height * 0.35), entity.width, entity.height * 0.65],
+        context.transform,
+        graphics
+    );
+}
+
+// Draw the text with the bitmap font, `pixel_size` is the size of each font pixel
+pub fn draw_text(text: &str, x: f64, y: f64, pixel_size: f64, color: [f32; 4], context: Context, graphics: &mut G2d) {
+    for (index, character) in text.chars().enumerate() {
+        let glyph_x = x + (index * (GLYPH_WIDTH + 1)) as f64 * pixel_size;
+
+        if let Some(rows) = glyph(character.to_ascii_uppercase()) {
+            for (row, bits) in rows.iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                        rectangle(
+                            color,
+                            [glyph_x + (col as f64 * pixel_size), y + (row as f64 * pixel_size), pixel_size, pixel_size],
+                            context.transform,
+                            graphics
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Points centered where the UFO was destroyed
+pub fn draw_score_popup(popup: &ScorePopup, context: Context, graphics: &mut G2d) {
+    let pixel_size = 2.0;
+    let text = popup.score.to_string();
+    let x = popup.x - (text_width(&text) as f64 * pixel_size / 2.0);
+
+    draw_text(&text, x, popup.y, pixel_size, UFO_COLOR, context, graphics);
+}
+
+// Score, high score and wave on the top of the screen, remaining lives as cannons on the bottom
+pub fn draw_hud(assets: &AssetManager, hud: &Hud, width: f64, height: f64, context: Context, graphics: &mut G2d) {
+    let pixel_size = 2.0;
+    let margin = 8.0;
+
+    draw_text(&hud.score_text(), margin, margin, pixel_size, TEXT_COLOR, context, graphics);
+
+    let high_score_text = hud.high_score_text();
+    let high_score_x = (width - (text_width(&high_score_text) as f64 * pixel_size)) / 2.0;
+    draw_text(&high_score_text, high_score_x, margin, pixel_size, TEXT_COLOR, context, graphics);
+
+    let wave_text = hud.wave_text();
+    let wave_x = width - margin - (text_width(&wave_text) as f64 * pixel_size);
+    draw_text(&wave_text, wave_x, margin, pixel_size, TEXT_COLOR, context, graphics);
+
+    let level_text = hud.level_text();
+    let level_x = width - margin - (text_width(&level_text) as f64 * pixel_size);
+    draw_text(&level_text, level_x, margin + (GLYPH_HEIGHT as f64 + 2.0) * pixel_size, pixel_size, TEXT_COLOR, context, graphics);
+
+    // Lives
+    let icon_scale = 0.5;
+    let icon_y = height - margin - (32.0 * icon_scale);
+    draw_text(&hud.lives_text(), margin, icon_y + 3.0, pixel_size, TEXT_COLOR, context, graphics);
+
+    if let Some(texture) = assets.get_sprite_texture(Sprite::Cannon) {
+        for life in 0..hud.lives {
+            let icon_x = margin + 16.0 + (life as f64 * 20.0);
+            let transform = context.transform.trans(icon_x, icon_y).scale(icon_scale, icon_scale);
+            image(texture, transform, graphics);
+        }
+    }
+}
+
+fn draw_centered_text(text: &str, y: f64, width: f64, pixel_size: f64, context: Context, graphics: &mut G2d) {
+    let x = (width - (text_width(text) as f64 * pixel_size)) / 2.0;
+    draw_text(text, x, y, pixel_size, TEXT_COLOR, context, graphics);
+}
+
+// Title, pause and game over screens drawn over the world
+pub fn draw_state(session: &Session, width: f64, height: f64, context: Context, graphics: &mut G2d) {
+    let center_y = height / 2.0;
+
+    match &session.state {
+        GameState::Title => {
+            draw_centered_text("SPACE INVADERS", center_y - 60.0, width, 5.0, context, graphics);
+            draw_centered_text("PRESS ENTER TO PLAY", center_y + 10.0, width, 2.0, context, graphics);
+            draw_centered_text("ARROWS MOVE - SPACE FIRES - P PAUSES - R RESTARTS", center_y + 40.0, width, 2.0, context, graphics);
+        },
+        GameState::Paused => {
+            draw_centered_text("PAUSED", center_y - 20.0, width, 4.0, context, graphics);
+        },
+        GameState::GameOver => {
+            draw_centered_text("GAME OVER", center_y - 100.0, width, 4.0, context, graphics);
+            for (index, high_score) in session.high_scores.iter().enumerate() {
+                let text = format!("{}. {:<3} {:05}", index + 1, high_score.name, high_score.score);
+                draw_centered_text(&text, center_y - 50.0 + (index as f64 * 16.0), width, 2.0, context, graphics);
+            }
+            draw_centered_text("PRESS ENTER TO PLAY AGAIN", center_y + 50.0, width, 2.0, context, graphics);
+        },
+        GameState::HighScoreEntry { name } => {
+            draw_centered_text("NEW HIGH SCORE!", center_y - 40.0, width, 4.0, context, graphics);
+            draw_centered_text(&format!("NAME: {:-<3}", name), center_y, width, 3.0, context, graphics);
+        },
+        GameState::Playing | GameState::LifeLost => {},
+    }
+}
@@ -0,0 +1,302 @@
+use crate::error::GameError;
+use crate::world::{InputState, World};
+use std::fs;
+use std::path::Path;
+
+// File layout: magic, version, seed (u64 LE), config fingerprint (u64 LE), level count (u16 LE),
+// then each level name as [length (u16 LE), UTF-8 bytes], then runs of [input flags (u8), tick count (u16 LE)]
+const MAGIC: &[u8; 4] = b"INVR";
+pub const REPLAY_VERSION: u8 = 2;
+
+const LEFT_FLAG: u8 = 1;
+const RIGHT_FLAG: u8 = 2;
+const FIRE_FLAG: u8 = 4;
+
+// Seed and input of every world step of a match, with the levels and config it was played with
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub levels: Vec<String>,
+    pub fingerprint: u64,
+    pub inputs: Vec<InputState>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, levels: Vec<String>, fingerprint: u64) -> Replay {
+        Replay {
+            seed,
+            levels,
+            fingerprint,
+            inputs: vec![],
+        }
+    }
+
+    // Empty replay of a match of the world
+    pub fn record(world: &World) -> Replay {
+        Replay::new(world.seed, level_names(world), fingerprint(world))
+    }
+
+    // The inputs only replay the same match on the levels and config they were recorded with
+    pub fn check(&self, world: &World) -> Result<(), GameError> {
+        let levels = level_names(world);
+        if self.levels != levels {
+            return Err(GameError::ReplayFile(format!(
+                "recorded on the levels [{}], not [{}]", self.levels.join(", "), levels.join(", ")
+            )));
+        }
+        if self.fingerprint != fingerprint(world) {
+            return Err(GameError::ReplayFile(String::from("recorded with a different config or level files")));
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, input: InputState) {
+        self.inputs.push(input);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(REPLAY_VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.fingerprint.to_le_bytes());
+        bytes.extend_from_slice(&(self.levels.len() as u16).to_le_bytes());
+        for level in self.levels.iter() {
+            bytes.extend_from_slice(&(level.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(level.as_bytes());
+        }
+
+        let mut runs: Vec<(u8, u16)> = vec![];
+        for input in self.inputs.iter() {
+            let flags = input_to_flags(input);
+            match runs.last_mut() {
+                Some(run) if run.0 == flags && run.1 < u16::MAX => run.1 += 1,
+                _ => runs.push((flags, 1)),
+            }
+        }
+
+        for (flags, count) in runs {
+            bytes.push(flags);
+            bytes.extend_from_slice(&count.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, GameError> {
+        if bytes.len() < 5 || &bytes[0..4] != MAGIC {
+            return Err(GameError::ReplayFile(String::from("not a replay file")));
+        }
+        if bytes[4] != REPLAY_VERSION {
+            return Err(GameError::ReplayFile(format!("unsupported version {}, expected {}", bytes[4], REPLAY_VERSION)));
+        }
+
+        let mut reader = Reader { bytes, offset: 5 };
+        let seed = reader.read_u64()?;
+        let fingerprint = reader.read_u64()?;
+        let mut levels = vec![];
+        for _ in 0..reader.read_u16()? {
+            let length = reader.read_u16()? as usize;
+            let name = String::from_utf8(reader.read(length)?.to_vec())
+                .map_err(|_| GameError::ReplayFile(String::from("level name is not UTF-8")))?;
+            levels.push(name);
+        }
+        let mut replay = Replay::new(seed, levels, fingerprint);
+
+        let runs = bytes[reader.offset..].chunks_exact(3);
+        if !runs.remainder().is_empty() {
+            return Err(GameError::ReplayFile(String::from("truncated input data")));
+        }
+
+        // The runs written by to_bytes are never empty and only use the known flags
+        for run in runs {
+            if run[0] & !(LEFT_FLAG | RIGHT_FLAG | FIRE_FLAG) != 0 {
+                return Err(GameError::ReplayFile(format!("unknown input flags {:#04x}", run[0])));
+            }
+            let input = flags_to_input(run[0]);
+            let count = u16::from_le_bytes([run[1], run[2]]);
+            if count == 0 {
+                return Err(GameError::ReplayFile(String::from("empty input run")));
+            }
+
+            for _ in 0..count {
+                replay.push(input);
+            }
+        }
+
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GameError> {
+        fs::write(path, self.to_bytes())
+            .map_err(|error| GameError::ReplayFile(format!("{}: {}", path.display(), error)))
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, GameError> {
+        let bytes = fs::read(path)
+            .map_err(|error| GameError::ReplayFile(format!("{}: {}", path.display(), error)))?;
+        Replay::from_bytes(&bytes)
+    }
+}
+
+// Reads the header fields in order, a missing field means a truncated header
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, length: usize) -> Result<&'a [u8], GameError> {
+        let field = self.bytes.get(self.offset..self.offset + length)
+            .ok_or_else(|| GameError::ReplayFile(String::from("truncated header")))?;
+        self.offset += length;
+        Ok(field)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, GameError> {
+        let mut field = [0; 2];
+        field.copy_from_slice(self.read(2)?);
+        Ok(u16::from_le_bytes(field))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, GameError> {
+        let mut field = [0; 8];
+        field.copy_from_slice(self.read(8)?);
+        Ok(u64::from_le_bytes(field))
+    }
+}
+
+fn level_names(world: &World) -> Vec<String> {
+    world.levels.iter().map(|level| level.name.clone()).collect()
+}
+
+// FNV-1a hash of everything besides the seed and the inputs that decides how a match plays
+fn fingerprint(world: &World) -> u64 {
+    let settings = format!("{:?}{:?}", world.config, world.levels);
+    settings.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+fn input_to_flags(input: &InputState) -> u8 {
+    let mut flags = 0;
+    if input.left { flags |= LEFT_FLAG; }
+    if input.right { flags |= RIGHT_FLAG; }
+    if input.fire { flags |= FIRE_FLAG; }
+    flags
+}
+
+fn flags_to_input(flags: u8) -> InputState {
+    InputState {
+        left: flags & LEFT_FLAG != 0,
+        right: flags & RIGHT_FLAG != 0,
+        fire: flags & FIRE_FLAG != 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::level::LevelLoader;
+
+    fn input(left: bool, right: bool, fire: bool) -> InputState {
+        InputState { left, right, fire }
+    }
+
+    fn flags(replay: &Replay) -> Vec<u8> {
+        replay.inputs.iter().map(input_to_flags).collect()
+    }
+
+    fn header(seed: u64) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(REPLAY_VERSION);
+        bytes.extend_from_slice(&seed.to_le_bytes());
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut replay = Replay::new(0x0123_4567_89ab_cdef, vec![String::from("Classic"), String::from("Arrow")], 99);
+        for step in 0..200 {
+            replay.push(input(step % 7 < 3, step % 11 == 0, step % 5 == 0));
+        }
+
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(loaded.seed, replay.seed);
+        assert_eq!(loaded.levels, replay.levels);
+        assert_eq!(loaded.fingerprint, replay.fingerprint);
+        assert_eq!(flags(&loaded), flags(&replay));
+    }
+
+    #[test]
+    fn empty_replay_is_only_a_header() {
+        let replay = Replay::new(42, vec![], 0);
+        let bytes = replay.to_bytes();
+
+        assert_eq!(bytes, header(42));
+        let loaded = Replay::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.seed, 42);
+        assert!(loaded.inputs.is_empty());
+    }
+
+    #[test]
+    fn long_runs_are_split() {
+        let mut replay = Replay::new(1, vec![], 0);
+        for _ in 0..(u16::MAX as usize + 10) {
+            replay.push(input(true, false, false));
+        }
+
+        let bytes = replay.to_bytes();
+        assert_eq!(bytes.len(), header(1).len() + 6);
+        assert_eq!(Replay::from_bytes(&bytes).unwrap().inputs.len(), u16::MAX as usize + 10);
+    }
+
+    #[test]
+    fn rejects_bad_header() {
+        assert!(Replay::from_bytes(b"INVR").unwrap_err().to_string().contains("not a replay file"));
+        assert!(Replay::from_bytes(b"NOPE\x01\0\0\0\0\0\0\0\0").unwrap_err().to_string().contains("not a replay file"));
+
+        let mut bytes = header(1);
+        bytes[4] = REPLAY_VERSION + 1;
+        assert!(Replay::from_bytes(&bytes).unwrap_err().to_string().contains("unsupported version"));
+
+        let mut truncated = header(1);
+        truncated.truncate(truncated.len() - 1);
+        assert!(Replay::from_bytes(&truncated).unwrap_err().to_string().contains("truncated header"));
+
+        let mut bad_name = header(1);
+        let levels = bad_name.len() - 2;
+        bad_name[levels] = 1;
+        bad_name.extend_from_slice(&[1, 0, 0xff]);
+        assert!(Replay::from_bytes(&bad_name).unwrap_err().to_string().contains("not UTF-8"));
+    }
+
+    #[test]
+    fn rejects_bad_runs() {
+        let mut truncated = header(1);
+        truncated.extend_from_slice(&[LEFT_FLAG, 1, 0, FIRE_FLAG]);
+        assert!(Replay::from_bytes(&truncated).unwrap_err().to_string().contains("truncated"));
+
+        let mut empty_run = header(1);
+        empty_run.extend_from_slice(&[LEFT_FLAG, 0, 0]);
+        assert!(Replay::from_bytes(&empty_run).unwrap_err().to_string().contains("empty input run"));
+
+        let mut unknown_flags = header(1);
+        unknown_flags.extend_from_slice(&[0x08, 1, 0]);
+        assert!(Replay::from_bytes(&unknown_flags).unwrap_err().to_string().contains("unknown input flags"));
+    }
+
+    #[test]
+    fn checks_the_levels_and_config() {
+        let mut world = World::new(Config::default(), 1);
+        let replay = Replay::record(&world);
+        assert!(replay.check(&world).is_ok());
+
+        world.config.cannon.lives += 1;
+        assert!(replay.check(&world).unwrap_err().to_string().contains("different config"));
+
+        world.levels = LevelLoader::new(640, 284.0).campaign().unwrap();
+        assert!(replay.check(&world).unwrap_err().to_string().contains("recorded on the levels []"));
+    }
+}
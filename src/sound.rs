@@ -0,0 +1,45 @@
+extern crate rodio;
+
+use crate::world::{WorldEvent, MARCH_NOTES};
+use rodio::source::{SineWave, Source};
+use rodio::{OutputStream, OutputStreamHandle};
+use std::time::Duration;
+
+// Each march note is a short, quiet tone
+const NOTE_TIME: Duration = Duration::from_millis(90);
+const NOTE_VOLUME: f32 = 0.3;
+
+// Plays the sounds of the world events, the game stays silent without an audio device
+pub struct Sound {
+    // Nothing is heard once the stream is dropped
+    output: Option<(OutputStream, OutputStreamHandle)>,
+}
+
+impl Sound {
+    // Opens the default audio device
+    pub fn open() -> Sound {
+        Sound {
+            output: OutputStream::try_default().ok(),
+        }
+    }
+
+    pub fn play_events(&self, events: &[WorldEvent]) {
+        for event in events {
+            if let WorldEvent::MarchBeat(note) = event {
+                self.play_note(MARCH_NOTES[*note]);
+            }
+        }
+    }
+
+    // `frequency` in Hz
+    fn play_note(&self, frequency: f64) {
+        if let Some((_, handle)) = self.output.as_ref() {
+            let note = SineWave::new(frequency as f32)
+                .take_duration(NOTE_TIME)
+                .amplify(NOTE_VOLUME);
+
+            // A note that can not be played is skipped
+            let _ = handle.play_raw(note);
+        }
+    }
+}
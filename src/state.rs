@@ -0,0 +1,290 @@
+use crate::error::GameError;
+use crate::replay::Replay;
+use crate::world::{InputState, World, WorldEvent, FIXED_TIMESTEP};
+
+// Steps run at most by a single advance, so a long pause does not stall the game catching up
+const MAX_STEPS_PER_ADVANCE: u32 = 5;
+const HIGH_SCORES_SIZE: usize = 5;
+const HIGH_SCORE_NAME_SIZE: usize = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameState {
+    Title,
+    Playing,
+    Paused,
+    LifeLost,
+    GameOver,
+    HighScoreEntry { name: String },
+}
+
+// Menu keys sent by the front-ends, letters double as commands outside the high score entry
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StateInput {
+    Letter(char),
+    Confirm,
+    Erase,
+}
+
+#[derive(Clone, Debug)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+}
+
+// Drives the world through the game states, shared by every front-end
+pub struct Session {
+    pub world: World,
+    pub state: GameState,
+    pub high_scores: Vec<HighScore>,
+
+    // Inputs of the current match, and the replay being played back with its next step
+    pub recording: Replay,
+    playback: Option<(Replay, usize)>,
+
+    accumulator: f64,
+    pending_fire: bool,
+    // World events of the steps taken by the last advance, for the front-ends to play or show
+    events: Vec<WorldEvent>,
+}
+
+impl Session {
+    pub fn new(world: World) -> Session {
+        let recording = Replay::record(&world);
+
+        Session {
+            world,
+            state: GameState::Title,
+            high_scores: vec![],
+
+            recording,
+            playback: None,
+
+            accumulator: 0.0,
+            pending_fire: false,
+            events: vec![],
+        }
+    }
+
+    pub fn handle_input(&mut self, input: StateInput) -> Result<(), GameError> {
+        match (&mut self.state, input) {
+            (GameState::Title, StateInput::Confirm) | (GameState::GameOver, StateInput::Confirm) => {
+                self.reset()?;
+            },
+            (GameState::Playing, StateInput::Letter('P')) => {
+                self.state = GameState::Paused;
+            },
+            (GameState::Paused, StateInput::Letter('P')) | (GameState::Paused, StateInput::Confirm) => {
+                self.state = GameState::Playing;
+            },
+            (GameState::Playing, StateInput::Letter('R'))
+            | (GameState::Paused, StateInput::Letter('R'))
+            | (GameState::GameOver, StateInput::Letter('R')) => {
+                self.reset()?;
+            },
+            (GameState::HighScoreEntry { name }, StateInput::Letter(letter))
+                if name.len() < HIGH_SCORE_NAME_SIZE && letter.is_ascii_alphanumeric() => {
+                name.push(letter.to_ascii_uppercase());
+            },
+            (GameState::HighScoreEntry { name }, StateInput::Erase) => {
+                name.pop();
+            },
+            (GameState::HighScoreEntry { name }, StateInput::Confirm) if !name.is_empty() => {
+                let name = name.clone();
+                self.add_high_score(name);
+                self.state = GameState::GameOver;
+            },
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    // Run as many fixed steps as fit in the elapsed time, in seconds, keeping the remainder for the next call
+    pub fn advance(&mut self, input: InputState, elapsed: f64) -> Result<(), GameError> {
+        self.events.clear();
+
+        // A shot is kept until a step fires it
+        self.pending_fire = self.pending_fire || input.fire;
+        self.accumulator = (self.accumulator + elapsed).min(FIXED_TIMESTEP * MAX_STEPS_PER_ADVANCE as f64);
+
+        while self.accumulator >= FIXED_TIMESTEP {
+            let mut step_input = InputState { fire: self.pending_fire, ..input };
+            self.pending_fire = false;
+
+            // The recorded input replaces the player one, a step is only taken while the world runs
+            if self.is_world_running() {
+                if let Some((replay, next_step)) = self.playback.as_mut() {
+                    step_input = replay.inputs.get(*next_step).copied().unwrap_or_default();
+                    *next_step += 1;
+                }
+            }
+
+            self.update(step_input, FIXED_TIMESTEP)?;
+            self.accumulator -= FIXED_TIMESTEP;
+        }
+
+        Ok(())
+    }
+
+    // Advance the world while playing or losing a life, `dt` is the elapsed time in seconds
+    pub fn update(&mut self, input: InputState, dt: f64) -> Result<(), GameError> {
+        match self.state {
+            GameState::Playing | GameState::LifeLost => {
+                self.recording.push(input);
+
+                let events = self.world.step(input, dt)?;
+                for event in events.iter() {
+                    match event {
+                        WorldEvent::LifeLost => {
+                            self.state = GameState::LifeLost;
+                        },
+                        WorldEvent::CannonRespawned => {
+                            self.state = GameState::Playing;
+                        },
+                        WorldEvent::GameOver => {
+                            self.state = self.game_over_state();
+                            break;
+                        },
+                        WorldEvent::WaveCleared(_) | WorldEvent::MarchBeat(_) => {},
+                    }
+                }
+                self.events.extend(events);
+            },
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn get_events(&self) -> &[WorldEvent] {
+        &self.events
+    }
+
+    // Start a new match from a clean world, keeping the high scores
+    pub fn reset(&mut self) -> Result<(), GameError> {
+        self.world.reset()?;
+        self.state = GameState::Playing;
+        self.recording = Replay::record(&self.world);
+        self.playback = None;
+        Ok(())
+    }
+
+    // Start a match with the seed of the replay, driven by its inputs instead of the player
+    pub fn start_playback(&mut self, replay: Replay) -> Result<(), GameError> {
+        replay.check(&self.world)?;
+        self.world.seed = replay.seed;
+        self.reset()?;
+        self.playback = Some((replay, 0));
+        Ok(())
+    }
+
+    pub fn is_playing_back(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    pub fn is_world_running(&self) -> bool {
+        self.state == GameState::Playing || self.state == GameState::LifeLost
+    }
+
+    fn game_over_state(&self) -> GameState {
+        let score = self.world.game_score;
+        let qualifies = !self.is_playing_back() && score > 0 && (self.high_scores.len() < HIGH_SCORES_SIZE
+            || self.high_scores.iter().any(|high_score| score > high_score.score));
+
+        if qualifies {
+            GameState::HighScoreEntry { name: String::new() }
+        } else {
+            GameState::GameOver
+        }
+    }
+
+    fn add_high_score(&mut self, name: String) {
+        self.high_scores.push(HighScore { name, score: self.world.game_score });
+        self.high_scores.sort_by_key(|high_score| std::cmp::Reverse(high_score.score));
+        self.high_scores.truncate(HIGH_SCORES_SIZE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::entities::{Shot, ShotKind, Sprite};
+
+    #[test]
+    fn advance_exposes_the_march_beat() {
+        let mut config = Config::default();
+        config.aliens.step_march = true;
+        let mut session = Session::new(World::new(config, 1));
+        session.handle_input(StateInput::Confirm).unwrap();
+
+        let mut notes = vec![];
+        for _ in 0..600 {
+            session.advance(InputState::default(), FIXED_TIMESTEP).unwrap();
+            for event in session.get_events() {
+                if let WorldEvent::MarchBeat(note) = event {
+                    notes.push(*note);
+                }
+            }
+        }
+
+        assert!(notes.len() >= 4, "only {} beats", notes.len());
+        assert_eq!(notes[..4], [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn events_only_last_until_the_next_advance() {
+        let mut session = Session::new(World::new(Config::default(), 1));
+        session.handle_input(StateInput::Confirm).unwrap();
+        session.world.cannon.life = 1;
+        session.world.alien_shots.clear();
+
+        // Put an alien shot on the cannon
+        let cannon = &session.world.cannon.entity;
+        let shot = Shot::new(cannon.x, cannon.y, Sprite::AlienShot(ShotKind::Straight), 0.0, (8.0, 8.0), (1.0, 1.0));
+        session.world.alien_shots.push(shot);
+
+        session.advance(InputState::default(), FIXED_TIMESTEP).unwrap();
+        assert_eq!(session.get_events(), [WorldEvent::LifeLost]);
+
+        session.advance(InputState::default(), FIXED_TIMESTEP).unwrap();
+        assert!(session.get_events().is_empty());
+    }
+
+    // Advance one step at a time until the match is over
+    fn play_to_game_over(session: &mut Session, input: fn(u32) -> InputState) {
+        let mut steps = 0;
+        while session.is_world_running() {
+            assert!(steps < 100_000, "the match never ended");
+            session.advance(input(steps), FIXED_TIMESTEP).unwrap();
+            steps += 1;
+        }
+    }
+
+    #[test]
+    fn playback_replays_the_recorded_match() {
+        let mut session = Session::new(World::new(Config::default(), 3));
+        session.handle_input(StateInput::Confirm).unwrap();
+        play_to_game_over(&mut session, |step| InputState {
+            left: (step / 90) % 2 == 0,
+            right: (step / 90) % 2 == 1,
+            fire: step % 20 == 0,
+        });
+        let replay = Replay::from_bytes(&session.recording.to_bytes()).unwrap();
+
+        // The seed comes from the replay and the player input is ignored
+        let mut playback = Session::new(World::new(Config::default(), 4));
+        playback.start_playback(replay.clone()).unwrap();
+        play_to_game_over(&mut playback, |_| InputState { left: true, right: false, fire: true });
+
+        assert!(session.world.game_score > 0);
+        assert_eq!(playback.world.game_score, session.world.game_score);
+        assert_eq!(playback.world.wave, session.world.wave);
+        assert_eq!(playback.state, GameState::GameOver);
+
+        let mut config = Config::default();
+        config.cannon.lives += 1;
+        let mut other = Session::new(World::new(config, 3));
+        assert!(other.start_playback(replay).is_err());
+    }
+}
@@ -0,0 +1,759 @@
+use crate::behavior::{behavior, BehaviorContext};
+use crate::config::Config;
+use crate::director::FiringDirector;
+use crate::entities::{Alien, AlienVariant, Barricade, Cannon, Entity, Shot, Ufo};
+use crate::error::GameError;
+use crate::formation::Formation;
+use crate::level::Level;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Reverse;
+
+// The world always advances by this many seconds per step, speeds are in pixels per second
+pub const FIXED_TIMESTEP: f64 = 1.0 / 60.0;
+
+// Arcade UFO scores, chosen by the number of cannon shots fired
+const UFO_SCORES: [u32; 15] = [100, 50, 50, 100, 150, 100, 100, 50, 300, 100, 100, 100, 50, 150, 100];
+const SCORE_POPUP_TIME: f64 = 1.0;
+
+// Damage of each cannon shot, see the alien hit points and armor
+const CANNON_SHOT_DAMAGE: u32 = 2;
+
+// Rising march beat of the step-wise march, in Hz, a note each time the whole formation has stepped
+pub const MARCH_NOTES: [f64; 4] = [98.0, 110.0, 123.47, 130.81];
+// The beat never plays faster than this, in seconds, even when a single alien is left
+const MIN_MARCH_BEAT_INTERVAL: f64 = 0.1;
+
+// Snapshot of the player controls for a single simulation step
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InputState {
+    pub left: bool,
+    pub right: bool,
+    pub fire: bool,
+}
+
+// Things that happened during a step, for the front-ends and the game state
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorldEvent {
+    LifeLost,
+    CannonRespawned,
+    WaveCleared(u32),
+    GameOver,
+    // Index in MARCH_NOTES of the note to play
+    MarchBeat(usize),
+}
+
+// Points shown for a while where the UFO was destroyed
+#[derive(Clone, Copy, Debug)]
+pub struct ScorePopup {
+    pub x: f64,
+    pub y: f64,
+    pub score: u32,
+    pub remaining_time: f64,
+}
+
+// Renderer-independent game simulation
+pub struct World {
+    pub config: Config,
+    pub width: u32,
+    pub height: u32,
+
+    pub game_score: u32,
+    pub high_score: u32,
+    pub game_over: bool,
+    pub wave: u32,
+
+    pub cannon: Cannon,
+    pub formation: Formation,
+    // Waves play these levels in order, the classic formation is used when there are none
+    pub levels: Vec<Level>,
+    // Name of the level of the current wave
+    pub level_name: Option<String>,
+    pub aliens: Vec<Vec<Alien>>,
+    pub barricades: Vec<Barricade>,
+    pub ufo: Option<Ufo>,
+    pub ufo_score_popup: Option<ScorePopup>,
+
+    pub cannon_shots: Vec<Shot>,
+    pub cannon_shot_count: u32,
+    pub alien_shots: Vec<Shot>,
+    pub aliens_movement_speed: f64,
+    pub aliens_height_modifier: f64,
+    pub firing: FiringDirector,
+
+    ufo_elapsed_time: f64,
+    ufo_interval: f64,
+
+    // Step-wise march: place in the march order of the last alien moved, if the current step goes down,
+    // next note of the beat and time since the last one
+    march_cursor: Option<(Reverse<u32>, u32)>,
+    march_dropping: bool,
+    march_note: usize,
+    march_beat_time: f64,
+
+    events: Vec<WorldEvent>,
+
+    // Every random choice comes from this generator, a seed and the inputs reproduce a match
+    pub seed: u64,
+    rng: StdRng,
+}
+
+impl World {
+    pub fn new(config: Config, seed: u64) -> World {
+        let width = config.window.width;
+        let height = config.window.height;
+
+        World {
+            width,
+            height,
+            game_score: 0,
+            high_score: 0,
+            game_over: false,
+            wave: 1,
+            cannon: new_cannon(&config),
+            formation: Formation::classic(width),
+            levels: vec![],
+            level_name: None,
+            aliens: vec![],
+            barricades: vec![],
+            ufo: None,
+            ufo_score_popup: None,
+            cannon_shots: vec![],
+            cannon_shot_count: 0,
+            alien_shots: vec![],
+
+            aliens_movement_speed: config.aliens.movement_speed,
+            aliens_height_modifier: config.aliens.height_modifier,
+            firing: FiringDirector::new(config.firing.clone(), config.aliens.shot_interval),
+
+            ufo_elapsed_time: 0.0,
+            ufo_interval: config.ufo.max_interval,
+
+            march_cursor: None,
+            march_dropping: false,
+            march_note: 0,
+            march_beat_time: 0.0,
+
+            events: vec![],
+            config,
+
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // Start a new match: score, cannon, lives and speeds go back to their initial values
+    pub fn reset(&mut self) -> Result<(), GameError> {
+        self.game_score = 0;
+        self.game_over = false;
+        self.cannon = new_cannon(&self.config);
+        self.cannon_shot_count = 0;
+        self.ufo_score_popup = None;
+        self.events.clear();
+        self.rng = StdRng::seed_from_u64(self.seed);
+
+        self.spawn_wave(1)
+    }
+
+    // Place the formation of the given wave, lower and faster the higher the wave is
+    pub fn spawn_wave(&mut self, wave: u32) -> Result<(), GameError> {
+        let index = (wave.max(1) - 1) as usize;
+        let difficulty = index as u32;
+
+        // Past the last level the campaign starts over, with tougher aliens on each loop
+        let (level, loops) = match self.levels.len() {
+            0 => (None, 0),
+            count => (self.levels.get(index % count).cloned(), (index / count) as u32),
+        };
+
+        let waves = &self.config.waves;
+        let aliens = &self.config.aliens;
+        let mut formation = level.as_ref().map_or_else(|| self.formation.clone(), |level| level.formation.clone());
+        let origin = formation.get_origin();
+
+        formation = formation.origin(origin.0, origin.1 + (waves.drop * difficulty.min(waves.max_drops) as f64));
+        // Stronger aliens wave after wave, or on each loop of the levels
+        if waves.tougher_variants {
+            formation = match level {
+                None => formation.variant(wave_variant(wave)),
+                Some(_) => formation.tougher(loops),
+            };
+        }
+
+        let movement_speed = level.as_ref().and_then(|level| level.movement_speed).unwrap_or(aliens.movement_speed);
+        let shot_interval = level.as_ref().and_then(|level| level.shot_interval).unwrap_or(aliens.shot_interval);
+        self.aliens_movement_speed = movement_speed + (waves.speed_increase * difficulty as f64);
+        self.aliens_height_modifier = level.as_ref().and_then(|level| level.drop).unwrap_or(aliens.height_modifier);
+        self.firing.start_wave(
+            (shot_interval * waves.shot_interval_factor.powi(difficulty as i32)).max(waves.min_shot_interval),
+            level.as_ref().and_then(|level| level.shot_kind)
+        );
+
+        self.aliens = formation.build()?;
+        self.march_cursor = None;
+        self.march_dropping = false;
+        self.march_note = 0;
+        self.march_beat_time = 0.0;
+        self.level_name = level.as_ref().map(|level| level.name.clone());
+        self.place_barricades(level.and_then(|level| level.barricades));
+        self.wave = wave;
+        self.cannon_shots.clear();
+        self.alien_shots.clear();
+
+        self.ufo = None;
+        self.ufo_elapsed_time = 0.0;
+        self.ufo_interval = self.rng.gen_range(self.config.ufo.min_interval..self.config.ufo.max_interval);
+        Ok(())
+    }
+
+    // Place the barricades centered at the given fractions of the width, or spread them evenly
+    fn place_barricades(&mut self, centers: Option<Vec<f64>>) {
+        let count = self.config.barricades.count;
+        let y = self.config.get_barricades_y();
+        let centers = centers.unwrap_or_else(|| {
+            (1..=count).map(|i| i as f64 / (count + 1) as f64).collect()
+        });
+
+        self.barricades = centers.iter()
+            .map(|center| {
+                let mut barricade = Barricade::new(0.0, y);
+                barricade.entity.x = (center * self.width as f64) - (barricade.entity.width / 2.0);
+                barricade
+            })
+            .collect();
+    }
+
+    // Advance the simulation by one step, `dt` is the elapsed time in seconds
+    pub fn step(&mut self, input: InputState, dt: f64) -> Result<Vec<WorldEvent>, GameError> {
+        if self.game_over {
+            return Ok(vec![]);
+        }
+
+        // Everything is frozen while the cannon explodes
+        if self.cannon.is_exploding() {
+            self.cannon.exploding_time -= dt;
+
+            if !self.cannon.is_exploding() {
+                if self.cannon.life <= 0 {
+                    self.game_over = true;
+                    self.events.push(WorldEvent::GameOver);
+                } else {
+                    let x = (self.width as f64 - self.cannon.entity.width) / 2.0;
+                    self.cannon.respawn(x, self.config.cannon.invulnerable_time);
+                    self.events.push(WorldEvent::CannonRespawned);
+                }
+            }
+
+            return Ok(std::mem::take(&mut self.events));
+        }
+        self.cannon.invulnerable_time = (self.cannon.invulnerable_time - dt).max(0.0);
+
+        let mut movement_value;
+
+        // Cannon left move
+        if input.left {
+            movement_value = -self.cannon.get_movement_speed() * dt;
+            if (self.cannon.entity.x + movement_value) < 0.0 {
+                movement_value = 0.0;
+            }
+
+            self.cannon.move_x_axis(movement_value);
+        }
+
+        // Cannon right move
+        if input.right {
+            movement_value = self.cannon.get_movement_speed() * dt;
+            if (self.cannon.entity.x + movement_value) > (self.width as f64 - self.cannon.entity.width) {
+                movement_value = 0.0;
+            }
+            self.cannon.move_x_axis(movement_value);
+        }
+
+        // Cannon shot
+        if input.fire {
+            self.cannon_shots.push(self.cannon.shoot(-self.config.cannon.shot_speed));
+            self.cannon_shot_count += 1;
+        }
+
+        // Species behaviors, diving aliens crashing into the cannon cost a life
+        let context = self.behavior_context();
+        self.update_behaviors(context, dt);
+        if self.crash_diving_aliens() {
+            self.lose_life();
+            return Ok(std::mem::take(&mut self.events));
+        }
+
+        self.move_aliens(dt);
+        self.create_alien_shots(&context, dt)?;
+        self.move_ufo(dt);
+        self.move_shots(dt);
+
+        // Next wave when the formation is destroyed
+        if self.aliens.iter().all(|row| row.is_empty()) {
+            self.events.push(WorldEvent::WaveCleared(self.wave));
+            self.spawn_wave(self.wave + 1)?;
+        }
+
+        self.barricades.retain(|barricade| !barricade.is_destroyed());
+        self.high_score = self.high_score.max(self.game_score);
+
+        // Drop shots outside the window
+        self.cannon_shots.retain(|shot| shot.entity.y + shot.entity.height > 0.0 && !shot.entity.colided);
+        self.alien_shots.retain(|shot| (shot.entity.y + shot.entity.height <= self.height as f64) && !shot.entity.colided);
+
+        //Game over if the formation gets to the height of the cannon, diving aliens count where their slot is
+        for row in self.aliens.iter().rev() {
+            if let Some(first_alien) = row.first() {
+                if first_alien.get_slot_position().1 + first_alien.entity.height > self.cannon.entity.y {
+                    self.game_over = true
+                }
+                break
+            }
+        }
+
+        if self.game_over {
+            self.events.push(WorldEvent::GameOver);
+        }
+
+        Ok(std::mem::take(&mut self.events))
+    }
+
+    fn behavior_context(&self) -> BehaviorContext {
+        BehaviorContext {
+            cannon_x: self.cannon.entity.x + (self.cannon.entity.width / 2.0),
+            cannon_y: self.cannon.entity.y,
+            world_height: self.height as f64,
+            diving_count: self.aliens.iter().flat_map(|row| row.iter()).filter(|alien| alien.is_diving()).count(),
+        }
+    }
+
+    fn update_behaviors(&mut self, mut context: BehaviorContext, dt: f64) {
+        for alien in self.aliens.iter_mut().flat_map(|row| row.iter_mut()) {
+            let was_diving = alien.is_diving();
+            behavior(alien.get_species()).update(alien, &context, &mut self.rng, dt);
+
+            if !was_diving && alien.is_diving() {
+                context.diving_count += 1;
+            }
+        }
+    }
+
+    // Remove the diving aliens touching the cannon, returns if there was any
+    fn crash_diving_aliens(&mut self) -> bool {
+        if self.cannon.is_invulnerable() {
+            return false;
+        }
+
+        let cannon = &mut self.cannon;
+        let mut crashed = false;
+        for row in self.aliens.iter_mut() {
+            row.retain_mut(|alien| {
+                let crash = alien.is_diving() && verify_colision(&mut alien.entity, &mut cannon.entity);
+                crashed = crashed || crash;
+                !crash
+            });
+        }
+
+        crashed
+    }
+
+    // Blow up the cannon, every shot is cleared while it explodes
+    fn lose_life(&mut self) {
+        self.cannon.explode(self.config.cannon.explosion_time);
+        self.cannon_shots.clear();
+        self.alien_shots.clear();
+        self.events.push(WorldEvent::LifeLost);
+    }
+
+    fn move_aliens(&mut self, dt: f64) {
+        for alien in self.aliens.iter_mut().flat_map(|row| row.iter_mut()) {
+            alien.hit_flash_time = (alien.hit_flash_time - dt).max(0.0);
+        }
+
+        if self.config.aliens.step_march {
+            self.march_aliens(dt);
+        } else {
+            self.glide_aliens(dt);
+        }
+
+        // Aliens marching through the barricades erase them
+        for alien in self.aliens.iter().flat_map(|row| row.iter()).filter(|alien| !alien.is_diving()) {
+            for barricade in self.barricades.iter_mut() {
+                barricade.erase(&alien.entity);
+            }
+        }
+    }
+
+    // The whole formation moves a bit every step
+    fn glide_aliens(&mut self, dt: f64) {
+        let movement_value = self.aliens_movement_speed * dt;
+
+        for row in self.aliens.iter_mut() {
+            for alien in row.iter_mut() {
+                alien.move_slot(movement_value, 0.0);
+            }
+        }
+
+        // Verify if the most distant alien is at the maximum x, to go to the other side
+        if self.aliens_movement_speed > 0.0 {
+            let max_alien = self.aliens.iter()
+                .flat_map(|row| row.iter())
+                .max_by(|a, b| a.get_slot_position().0.partial_cmp(&b.get_slot_position().0).unwrap());
+
+            if let Some(distant_alien) = max_alien {
+                if distant_alien.get_slot_position().0 + distant_alien.entity.width + movement_value >= self.width as f64 {
+                    self.aliens_movement_speed = 0.0 - self.aliens_movement_speed;
+                    self.lower_aliens();
+                }
+            }
+        }
+
+        // Verify if the origin closest alien is at the minimum x, to go to the other side
+        if self.aliens_movement_speed < 0.0 {
+            let min_alien = self.aliens.iter()
+                .flat_map(|row| row.iter())
+                .min_by(|a, b| a.get_slot_position().0.partial_cmp(&b.get_slot_position().0).unwrap());
+
+            if let Some(closer_alien) = min_alien {
+                if closer_alien.get_slot_position().0 + movement_value <= 0.0 {
+                    self.aliens_movement_speed = 0.0 - self.aliens_movement_speed;
+                    self.lower_aliens();
+                }
+            }
+        }
+    }
+
+    // A single alien moves every step, bottom row first and left to right, like in the arcade,
+    // so the fewer aliens are left the sooner the formation has stepped and the faster it goes
+    fn march_aliens(&mut self, dt: f64) {
+        self.march_beat_time += dt;
+
+        let alien_pos = match self.next_marching_alien() {
+            Some(alien_pos) => alien_pos,
+            None => {
+                self.end_march_step();
+                match self.next_marching_alien() {
+                    Some(alien_pos) => alien_pos,
+                    None => return,
+                }
+            },
+        };
+
+        let alien = &mut self.aliens[alien_pos.0][alien_pos.1];
+        if self.march_dropping {
+            alien.move_slot(0.0, self.aliens_height_modifier * alien.entity.height);
+        } else {
+            alien.move_slot(self.config.aliens.march_step * self.aliens_movement_speed.signum(), 0.0);
+        }
+        alien.next_frame();
+        self.march_cursor = Some(march_order(alien));
+    }
+
+    // (row, index) of the first alien after the cursor in the march order
+    fn next_marching_alien(&self) -> Option<(usize, usize)> {
+        self.aliens.iter()
+            .enumerate()
+            .flat_map(|(row_index, row)| row.iter().enumerate().map(move |(index, alien)| ((row_index, index), march_order(alien))))
+            .filter(|(_, order)| self.march_cursor.is_none_or(|cursor| *order > cursor))
+            .min_by_key(|(_, order)| *order)
+            .map(|(alien_pos, _)| alien_pos)
+    }
+
+    // Every alien has moved: play the beat, and go down and turn around once the formation reaches a side
+    fn end_march_step(&mut self) {
+        if self.march_beat_time >= MIN_MARCH_BEAT_INTERVAL {
+            self.events.push(WorldEvent::MarchBeat(self.march_note));
+            self.march_note = (self.march_note + 1) % MARCH_NOTES.len();
+            self.march_beat_time = 0.0;
+        }
+
+        self.march_cursor = None;
+        if self.march_dropping {
+            self.march_dropping = false;
+            return;
+        }
+
+        let step = self.config.aliens.march_step;
+        let width = self.width as f64;
+        let mut aliens = self.aliens.iter().flat_map(|row| row.iter());
+        let at_side = if self.aliens_movement_speed > 0.0 {
+            aliens.any(|alien| alien.get_slot_position().0 + alien.entity.width + step >= width)
+        } else {
+            aliens.any(|alien| alien.get_slot_position().0 - step <= 0.0)
+        };
+
+        if at_side {
+            self.aliens_movement_speed = 0.0 - self.aliens_movement_speed;
+            self.march_dropping = true;
+        }
+    }
+
+    fn lower_aliens(&mut self) {
+        for row in self.aliens.iter_mut() {
+            for alien in row.iter_mut() {
+                alien.move_slot(0.0, self.aliens_height_modifier * alien.entity.height);
+            }
+        }
+    }
+
+    fn create_alien_shots(&mut self, context: &BehaviorContext, dt: f64) -> Result<(), GameError> {
+        let order = self.firing.update(&self.aliens, context.cannon_x, self.alien_shots.len(), &mut self.rng, dt);
+
+        if let Some(order) = order {
+            let alien_pos = order.alien;
+            let shooter_alien = self.aliens.get_mut(alien_pos.0)
+                .ok_or_else(|| GameError::InvalidFormation(format!("no row {}", alien_pos.0)))?
+                .get_mut(alien_pos.1)
+                .ok_or_else(|| GameError::InvalidFormation(format!("no col {} in row {}", alien_pos.1, alien_pos.0)))?;
+
+            let species_behavior = behavior(shooter_alien.get_species());
+            let kind = order.kind.unwrap_or_else(|| species_behavior.shot_kind());
+
+            let shot = species_behavior.shoot(shooter_alien, context, kind, self.config.aliens.shot_speed);
+            self.alien_shots.push(shot);
+        }
+
+        Ok(())
+    }
+
+    fn move_ufo(&mut self, dt: f64) {
+        if let Some(popup) = self.ufo_score_popup.as_mut() {
+            popup.remaining_time -= dt;
+            if popup.remaining_time <= 0.0 {
+                self.ufo_score_popup = None;
+            }
+        }
+
+        match self.ufo.as_mut() {
+            Some(ufo) => {
+                ufo.move_x_axis(dt);
+
+                // Gone when it leaves the screen
+                if ufo.entity.x + ufo.entity.width < 0.0 || ufo.entity.x > self.width as f64 {
+                    self.ufo = None;
+                }
+            },
+            None => {
+                self.ufo_elapsed_time += dt;
+
+                if self.ufo_elapsed_time >= self.ufo_interval {
+                    let ufo_config = &self.config.ufo;
+                    let mut ufo = Ufo::new(0.0, ufo_config.y, ufo_config.movement_speed);
+
+                    // Random direction, entering from the opposite side
+                    if self.rng.gen_bool(0.5) {
+                        ufo.entity.x = -ufo.entity.width;
+                    } else {
+                        ufo.entity.x = self.width as f64;
+                        ufo.entity.movement_speed = -ufo_config.movement_speed;
+                    }
+
+                    self.ufo = Some(ufo);
+                    self.ufo_elapsed_time = 0.0;
+                    self.ufo_interval = self.rng.gen_range(ufo_config.min_interval..ufo_config.max_interval);
+                }
+            },
+        }
+    }
+
+    fn move_shots(&mut self, dt: f64) {
+        // Move cannon shots
+        for shot in self.cannon_shots.iter_mut() {
+            shot.advance(dt);
+
+            // Colision with aliens, a shot hits a single one
+            for row in self.aliens.iter_mut() {
+                for alien in row.iter_mut() {
+                    if !shot.entity.colided && verify_colision(&mut shot.entity, &mut alien.entity) {
+                        alien.hit(CANNON_SHOT_DAMAGE);
+                    }
+                }
+
+                let before_colision_len = row.len();
+
+                // Add the score of each killed alien to the game score
+                for alien in row.iter().filter(|alien| alien.is_dead()) {
+                    self.game_score += alien.score;
+                }
+
+                // Remove killed
+                row.retain(|alien| !alien.is_dead());
+
+                let after_colision_len = row.len();
+
+                // Increase alien speed when one is killed, the step-wise march speeds up by itself
+                let diff = if self.config.aliens.step_march { 0 } else { before_colision_len - after_colision_len };
+
+                if self.aliens_movement_speed > 0.0 {self.aliens_movement_speed += self.config.aliens.kill_speed_increase * diff as f64;}
+                else {self.aliens_movement_speed -= self.config.aliens.kill_speed_increase * diff as f64;}
+            }
+
+            // Colision with the UFO
+            if let Some(ufo) = self.ufo.as_mut() {
+                if !shot.entity.colided && verify_colision(&mut shot.entity, &mut ufo.entity) {
+                    let score = UFO_SCORES[self.cannon_shot_count as usize % UFO_SCORES.len()];
+                    self.game_score += score;
+                    self.ufo_score_popup = Some(ScorePopup {
+                        x: ufo.entity.x + (ufo.entity.width / 2.0),
+                        y: ufo.entity.y,
+                        score,
+                        remaining_time: SCORE_POPUP_TIME,
+                    });
+                    self.ufo = None;
+                }
+            }
+
+            // Colision with other shots
+            for alien_shot in self.alien_shots.iter_mut() {
+                if !shot.entity.colided && !alien_shot.entity.colided {
+                    verify_colision(&mut shot.entity, &mut alien_shot.entity);
+                }
+            }
+
+            // Colision with the barricades
+            if !shot.entity.colided && self.barricades.iter_mut().any(|barricade| barricade.hit(&shot.entity)) {
+                shot.entity.colided = true;
+            }
+        }
+
+        // Move alien shots
+        let mut cannon_hit = false;
+        for shot in self.alien_shots.iter_mut() {
+            shot.advance(dt);
+
+            // Already stopped by a cannon shot
+            if shot.entity.colided {
+                continue;
+            }
+
+            // Colision with the barricades
+            if self.barricades.iter_mut().any(|barricade| barricade.hit(&shot.entity)) {
+                shot.entity.colided = true;
+                continue;
+            }
+
+            // Colision with the cannon
+            if !self.cannon.is_invulnerable() && verify_colision(&mut shot.entity, &mut self.cannon.entity) {
+                cannon_hit = true;
+                break;
+            }
+        }
+
+        // A single hit per volley
+        if cannon_hit {
+            self.lose_life();
+        }
+    }
+}
+
+fn new_cannon(config: &Config) -> Cannon {
+    Cannon::new(
+        0.0,
+        0.0 + config.window.height as f64 - config.cannon.y_offset,
+        config.cannon.movement_speed,
+        config.cannon.lives
+    )
+}
+
+// Aliens march bottom row first and left to right
+fn march_order(alien: &Alien) -> (Reverse<u32>, u32) {
+    (Reverse(alien.position.0), alien.position.1)
+}
+
+fn wave_variant(wave: u32) -> AlienVariant {
+    match wave {
+        0 | 1 => AlienVariant::Default,
+        2 => AlienVariant::White,
+        3 => AlienVariant::Pink,
+        _ => AlienVariant::Blue,
+    }
+}
+
+fn verify_colision(entity1: &mut Entity, entity2: &mut Entity) -> bool {
+    //Real measurements of the hitboxes
+    let entity1_real_x = (entity1.x, entity1.x + entity1.width);
+    let entity1_real_y = (entity1.y, entity1.y + entity1.height);
+
+    let entity2_real_x = (entity2.x,  entity2.x + entity2.width);
+    let entity2_real_y = (entity2.y, entity2.y + entity2.height);
+
+    // Verifica colisao em x
+    if (entity1_real_x.0 >= entity2_real_x.0 && entity1_real_x.0 <= entity2_real_x.1)
+        || (entity1_real_x.1 >= entity2_real_x.0 && entity1_real_x.1 <= entity2_real_x.1) {
+
+         // verifica colisao em y
+        if (entity1_real_y.0 >= entity2_real_y.0 && entity1_real_y.0 <= entity2_real_y.1)
+            || (entity1_real_y.1 >= entity2_real_y.0 && entity1_real_y.1 <= entity2_real_y.1) {
+            entity1.colided = true;
+            entity2.colided = true;
+            return true;
+        }
+
+        return false;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::Dive;
+
+    // A world whose formation is a single squid hovering out of the formation
+    fn world_with_diving_squid(step_march: bool) -> World {
+        let mut config = Config::default();
+        config.aliens.step_march = step_march;
+
+        let mut world = World::new(config, 1);
+        world.reset().unwrap();
+        world.aliens = vec![vec![world.aliens[0][0].clone()]];
+        world.aliens[0][0].dive = Some(Dive { velocity: (0.0, 0.0), offset: (0.0, 0.0), returning: false });
+        world
+    }
+
+    #[test]
+    fn diving_aliens_leave_the_formation() {
+        for step_march in [false, true] {
+            let mut world = world_with_diving_squid(step_march);
+            let start = (world.aliens[0][0].entity.x, world.aliens[0][0].entity.y);
+
+            for _ in 0..1200 {
+                world.step(InputState::default(), FIXED_TIMESTEP).unwrap();
+            }
+
+            // The squid stays where it dove, its slot keeps marching inside the window and goes down at the sides
+            let alien = &world.aliens[0][0];
+            let slot = alien.get_slot_position();
+            assert_eq!((alien.entity.x, alien.entity.y), start);
+            assert!(slot.0 >= 0.0 && slot.0 + alien.entity.width <= world.width as f64, "slot {:?} left the window", slot);
+            assert!(slot.1 > start.1, "the formation never turned around");
+        }
+    }
+
+    // Score, wave and number of steps of a match played with scripted inputs until the game is over
+    fn play_to_game_over(seed: u64) -> (u32, u32, u32) {
+        let mut world = World::new(Config::default(), seed);
+        world.reset().unwrap();
+
+        let mut steps = 0;
+        while !world.game_over {
+            assert!(steps < 100_000, "the match never ended");
+            let input = InputState {
+                left: (steps / 90) % 2 == 0,
+                right: (steps / 90) % 2 == 1,
+                fire: steps % 20 == 0,
+            };
+            world.step(input, FIXED_TIMESTEP).unwrap();
+            steps += 1;
+        }
+
+        (world.game_score, world.wave, steps)
+    }
+
+    #[test]
+    fn same_seed_and_inputs_play_the_same_match() {
+        let first = play_to_game_over(7);
+        assert_eq!(play_to_game_over(7), first);
+        assert!(first.0 > 0, "no alien was ever hit");
+    }
+}